@@ -12,7 +12,9 @@ use syn::{
     spanned::Spanned,
     token::Comma,
     Attribute, FnArg, Ident, Pat, PatType, ReturnType, Token, Type,
-    Visibility, punctuated::Punctuated, NestedMeta, Meta,
+    Visibility, punctuated::Punctuated, NestedMeta, Meta, AttributeArgs, Lit, Path,
+    TraitBound, TraitBoundModifier, TypeParamBound, TypePath, PathArguments,
+    Data, DataStruct, DeriveInput, Fields, GenericArgument,
 };
 
 macro_rules! extend_errors {
@@ -28,19 +30,127 @@ struct Service {
     attrs: Vec<Attribute>,
     vis: Visibility,
     ident: Ident,
+    supertraits: Vec<Supertrait>,
     rpcs: Vec<RpcMethod>,
 }
 
+/// A supertrait of a service. The items generated for a supertrait are assumed to have
+/// the default names and to be located alongside the supertrait, e.g., the service
+/// of `health::Health` is assumed to be `health::HealthService`.
+struct Supertrait {
+    path: Path,
+}
+
+impl Supertrait {
+    fn ident(&self) -> &Ident {
+        &self.path.segments.last().unwrap().ident
+    }
+
+    /// The name of the variant in the request and response enums
+    fn camel_case_ident(&self) -> Ident {
+        self.ident().unraw()
+    }
+
+    /// The name of the client accessor
+    fn snake_case_ident(&self) -> Ident {
+        let ident = self.ident();
+        Ident::new(&camel_to_snake(&ident.unraw().to_string()), ident.span())
+    }
+
+    /// The path to an item that was generated for the supertrait
+    fn generated(&self, suffix: &str) -> Path {
+        let mut path = self.path.clone();
+        let segment = path.segments.last_mut().unwrap();
+        segment.ident = format_ident!("{}{}", segment.ident.unraw(), suffix);
+        path
+    }
+}
+
+struct ServiceArgs {
+    client_ident: Option<Ident>,
+    service_ident: Option<Ident>,
+    request_ident: Option<Ident>,
+    response_ident: Option<Ident>,
+    derives: Vec<Path>,
+    krate: Path,
+    is_actor: bool,
+    has_context: bool,
+    is_cooperative: bool,
+}
+
 struct RpcMethod {
     is_async: bool,
+    is_ack: bool,
+    is_abortable: bool,
     attrs: Vec<Attribute>,
     ident: Ident,
     args: Vec<PatType>,
     transfer: HashSet<Ident>,
     post: HashSet<Ident>,
+    /* the arguments and the return value that are handles to remote objects or callbacks,
+       these are sent as the ports of their channels */
+    remote: HashSet<Ident>,
+    order_key: Option<Ident>,
     output: ReturnType,
 }
 
+impl RpcMethod {
+    /// The `cfg` attributes of the method, these need to be copied onto every item that is
+    /// generated for the method so that it is removed consistently
+    fn cfg_attrs(&self) -> impl Iterator<Item = &Attribute> {
+        self.attrs.iter().filter(|attr| attr.path.is_ident("cfg"))
+    }
+
+    /// The collection and the element type of a posted argument or return value that is an
+    /// `Option` or a `Vec` of Javascript values
+    fn posted_collection<'b>(&self, ident: &Ident, ty: &'b Type) -> Option<(Collection, &'b Type)> {
+        match self.post.contains(ident) && !self.remote.contains(ident) {
+            true => Collection::parse(ty),
+            false => None,
+        }
+    }
+}
+
+/// A collection of posted Javascript values, the number of values is serialized so that they
+/// can be taken from the posted values on the other side
+#[derive(Clone, Copy)]
+enum Collection {
+    Option,
+    Vec,
+}
+
+impl Collection {
+    fn parse(ty: &Type) -> Option<(Self, &Type)> {
+        let Type::Path(TypePath { qself: None, path }) = ty else {
+            return None
+        };
+        let segment = path.segments.last()?;
+        let collection = match segment.ident.to_string().as_str() {
+            "Option" => Self::Option,
+            "Vec" => Self::Vec,
+            _ => return None
+        };
+        let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+            return None
+        };
+        match arguments.args.iter().collect::<Vec<_>>()[..] {
+            [GenericArgument::Type(element)] => Some((collection, element)),
+            _ => None
+        }
+    }
+
+    /// Take `count` values of type `element` from the front of `js_args`
+    fn extract(self, krate: &Path, element: &Type, count: TokenStream2, js_args: TokenStream2) -> TokenStream2 {
+        let values = quote! {
+            (0..#count).map(|_| #krate::wasm_bindgen::JsCast::dyn_into::<#element>(#js_args.shift()).unwrap())
+        };
+        match self {
+            Self::Option => quote!(#values.next()),
+            Self::Vec => quote!(#values.collect::<std::vec::Vec<_>>()),
+        }
+    }
+}
+
 struct ServiceGenerator<'a> {
     trait_ident: &'a Ident,
     service_ident: &'a Ident,
@@ -49,30 +159,61 @@ struct ServiceGenerator<'a> {
     response_ident: &'a Ident,
     vis: &'a Visibility,
     attrs: &'a [Attribute],
+    supertraits: &'a [Supertrait],
     rpcs: &'a [RpcMethod],
     camel_case_idents: &'a [Ident],
+    derives: &'a [Path],
+    krate: &'a Path,
+    is_actor: bool,
+    has_context: bool,
+    is_cooperative: bool,
 }
 
 impl<'a> ServiceGenerator<'a> {
     fn enum_request(&self) -> TokenStream2 {
         let &Self {
+            krate,
+            derives,
             vis,
             request_ident,
             camel_case_idents,
+            supertraits,
             rpcs,
             ..
         } = self;
+        let serde_crate = serde_crate(krate);
+        let supertrait_variants = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let request_path = supertrait.generated("Request");
+                quote! {
+                    #camel_case_ident ( #request_path )
+                }
+            });
         let variants = rpcs.iter().zip(camel_case_idents.iter())
-            .map(|(RpcMethod { args, post, .. }, camel_case_ident)| {
+            .map(|(rpc @ RpcMethod { args, post, .. }, camel_case_ident)| {
+                let cfg_attrs = rpc.cfg_attrs();
                 let args_filtered = args.iter()
                     .filter(|arg| matches!(&*arg.pat, Pat::Ident(ident) if !post.contains(&ident.ident)));
+                /* only the number of values in posted collections is serialized */
+                let collection_counts = args.iter()
+                    .filter_map(|arg| match &*arg.pat {
+                        Pat::Ident(ident) if rpc.posted_collection(&ident.ident, &arg.ty).is_some() => {
+                            let ident = &ident.ident;
+                            Some(quote!(#ident: usize))
+                        },
+                        _ => None
+                    });
                 quote! {
-                    #camel_case_ident { #( #args_filtered ),* }
+                    #( #cfg_attrs )*
+                    #camel_case_ident { #( #args_filtered, )* #( #collection_counts, )* }
                 }
             });
         quote! {
-            #[derive(web_rpc::serde::Serialize, web_rpc::serde::Deserialize)]
+            #[derive(#krate::serde::Serialize, #krate::serde::Deserialize, #( #derives ),*)]
+            #[serde(crate = #serde_crate)]
             #vis enum #request_ident {
+                #( #supertrait_variants, )*
                 #( #variants ),*
             }
         }
@@ -80,24 +221,48 @@ impl<'a> ServiceGenerator<'a> {
 
     fn enum_response(&self) -> TokenStream2 {
         let &Self {
+            krate,
+            derives,
             vis,
             response_ident,
             camel_case_idents,
+            supertraits,
             rpcs,
             ..
         } = self;
+        let serde_crate = serde_crate(krate);
+        let supertrait_variants = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let response_path = supertrait.generated("Response");
+                quote! {
+                    #camel_case_ident ( #response_path )
+                }
+            });
         let variants = rpcs.iter().zip(camel_case_idents.iter())
-            .map(|(RpcMethod { output, post, .. }, camel_case_ident)| match output {
-                ReturnType::Type(_, ty) if !post.contains(&Ident::new("return", output.span())) => quote! {
-                    #camel_case_ident ( #ty )
-                },
-                _ => quote! {
-                    #camel_case_ident ( () )
-                },
+            .map(|(rpc @ RpcMethod { output, post, .. }, camel_case_ident)| {
+                let cfg_attrs = rpc.cfg_attrs();
+                let return_ident = Ident::new("return", output.span());
+                match output {
+                    ReturnType::Type(_, ty) if !post.contains(&return_ident) => quote! {
+                        #( #cfg_attrs )*
+                        #camel_case_ident ( #ty )
+                    },
+                    ReturnType::Type(_, ty) if rpc.posted_collection(&return_ident, ty).is_some() => quote! {
+                        #( #cfg_attrs )*
+                        #camel_case_ident ( usize )
+                    },
+                    _ => quote! {
+                        #( #cfg_attrs )*
+                        #camel_case_ident ( () )
+                    },
+                }
             });
         quote! {
-            #[derive(web_rpc::serde::Serialize, web_rpc::serde::Deserialize)]
+            #[derive(#krate::serde::Serialize, #krate::serde::Deserialize, #( #derives ),*)]
+            #[serde(crate = #serde_crate)]
             #vis enum #response_ident {
+                #( #supertrait_variants, )*
                 #( #variants ),*
             }
         }
@@ -106,11 +271,45 @@ impl<'a> ServiceGenerator<'a> {
     fn trait_service(&self) -> TokenStream2 {
         let &Self {
             attrs,
+            supertraits,
             rpcs,
             vis,
             trait_ident,
+            krate,
+            is_actor,
+            has_context,
+            is_cooperative,
             ..
         } = self;
+        let receiver = match is_actor {
+            true => quote!(&mut self),
+            false => quote!(&self),
+        };
+        /* services with a context receive it as the first parameter after the receiver, the
+           forwarding methods use a reserved name that cannot clash with the other arguments */
+        let (context_arg, forward_context_arg, forward_context) = match has_context {
+            true => (
+                quote!(ctx: &#krate::RequestContext,),
+                quote!(__ctx: &#krate::RequestContext,),
+                quote!(__ctx,)
+            ),
+            false => (quote!(), quote!(), quote!()),
+        };
+        /* async methods of cooperative services receive the cancellation token of their request */
+        let token_arg = |is_async: bool| match is_cooperative && is_async {
+            true => (
+                quote!(token: &#krate::CancellationToken,),
+                quote!(__token: &#krate::CancellationToken,),
+                quote!(__token,)
+            ),
+            false => (quote!(), quote!(), quote!()),
+        };
+        let supertrait_paths = supertraits.iter()
+            .map(|supertrait| &supertrait.path);
+        let supertrait_bounds = match supertraits.is_empty() {
+            true => quote!(),
+            false => quote!(: #( #supertrait_paths )+*),
+        };
 
         let unit_type: &Type = &parse_quote!(());
         let rpc_fns = rpcs.iter()
@@ -119,13 +318,14 @@ impl<'a> ServiceGenerator<'a> {
                     ReturnType::Type(_, ref ty) => ty,
                     ReturnType::Default => unit_type
                 };
+                let (token_arg, ..) = token_arg(*is_async);
                 let is_async = match is_async {
                     true => quote!(async),
                     false => quote!()
                 };
                 quote! {
                     #( #attrs )*
-                    #is_async fn #ident(&self, #( #args ),*) -> #output;
+                    #is_async fn #ident(#receiver, #context_arg #token_arg #( #args ),*) -> #output;
                 }
             });
 
@@ -135,6 +335,7 @@ impl<'a> ServiceGenerator<'a> {
                     ReturnType::Type(_, ref ty) => ty,
                     ReturnType::Default => unit_type
                 };
+                let (_, forward_token_arg, forward_token) = token_arg(*is_async);
                 let do_await = match is_async {
                     true => quote!(.await),
                     false => quote!()
@@ -149,92 +350,236 @@ impl<'a> ServiceGenerator<'a> {
                 });
                 quote! {
                     #( #attrs )*
-                    #is_async fn #ident(&self, #( #args ),*) -> #output {
-                        T::#ident(self, #( #forward_args ),*)#do_await
+                    #is_async fn #ident(#receiver, #forward_context_arg #forward_token_arg #( #args ),*) -> #output {
+                        T::#ident(self, #forward_context #forward_token #( #forward_args ),*)#do_await
                     }
                 }
             })
             .collect::<Vec<_>>();
 
+        /* actors require exclusive access and hence cannot be forwarded from shared pointers */
+        let forward_impls = match is_actor {
+            true => quote! {
+                impl<T> #trait_ident for std::boxed::Box<T> where T: #trait_ident {
+                    #( #forward_fns )*
+                }
+            },
+            false => quote! {
+                impl<T> #trait_ident for std::sync::Arc<T> where T: #trait_ident {
+                    #( #forward_fns )*
+                }
+                impl<T> #trait_ident for std::boxed::Box<T> where T: #trait_ident {
+                    #( #forward_fns )*
+                }
+                impl<T> #trait_ident for std::rc::Rc<T> where T: #trait_ident {
+                    #( #forward_fns )*
+                }
+            }
+        };
+
         quote! {
             #( #attrs )*
-            #vis trait #trait_ident {
+            #vis trait #trait_ident #supertrait_bounds {
                 #( #rpc_fns )*
             }
 
-            impl<T> #trait_ident for std::sync::Arc<T> where T: #trait_ident {
-                #( #forward_fns )*
-            }
-            impl<T> #trait_ident for std::boxed::Box<T> where T: #trait_ident {
-                #( #forward_fns )*
-            }
-            impl<T> #trait_ident for std::rc::Rc<T> where T: #trait_ident {
-                #( #forward_fns )*
-            }
+            #forward_impls
         }
     }
 
     fn struct_client(&self) -> TokenStream2 {
         let &Self {
+            krate,
             vis,
             client_ident,
             request_ident,
             response_ident,
             camel_case_idents,
+            supertraits,
             rpcs,
             ..
         } = self;
 
+        let supertrait_idents = supertraits.iter()
+            .map(Supertrait::snake_case_ident)
+            .collect::<Vec<_>>();
+        let supertrait_client_paths = supertraits.iter()
+            .map(|supertrait| supertrait.generated("Client"))
+            .collect::<Vec<_>>();
+        let supertrait_clients = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let client_path = supertrait.generated("Client");
+                quote! {
+                    <#client_path as std::convert::From<_>>::from(configuration.map(
+                        #request_ident::#camel_case_ident,
+                        |response| match response {
+                            #response_ident::#camel_case_ident(response) => Some(response),
+                            #[allow(unreachable_patterns)]
+                            _ => None
+                        }
+                    ))
+                }
+            });
+        let supertrait_accessors = supertraits.iter()
+            .map(|supertrait| {
+                let snake_case_ident = supertrait.snake_case_ident();
+                let client_path = supertrait.generated("Client");
+                let doc = format!(" Access the methods of the supertrait `{}`", supertrait.ident());
+                quote! {
+                    #[doc = #doc]
+                    #vis fn #snake_case_ident(&self) -> &#client_path {
+                        &self.#snake_case_ident
+                    }
+                }
+            });
+
+        let client_return_type = |RpcMethod { output, is_ack, is_abortable, .. }: &RpcMethod| match output {
+            ReturnType::Type(_, ref ty) => quote! {
+                #krate::client::RequestFuture<#ty>
+            },
+            _ if *is_ack => quote! {
+                #krate::client::RequestFuture<()>
+            },
+            _ if *is_abortable => quote! {
+                #krate::client::NotificationHandle
+            },
+            _ => quote!(())
+        };
+
+        /* the methods of the client are also provided by a trait, which is implemented by the
+           clients of the services that have this service's trait as a supertrait */
+        let methods_ident = format_ident!("{}Methods", client_ident);
+        let methods_doc = format!(
+            " The methods of [`{client_ident}`], these are also provided by the clients of the services that have \
+              `{}` as a supertrait",
+            self.trait_ident
+        );
+        let method_fns = rpcs.iter()
+            .map(|rpc @ RpcMethod { attrs, args, ident, .. }| {
+                let return_type = client_return_type(rpc);
+                let forward_args = args.iter().filter_map(|arg| match &*arg.pat {
+                    Pat::Ident(ident) => Some(&ident.ident),
+                    _ => None
+                });
+                quote! {
+                    #( #attrs )*
+                    fn #ident(&self, #( #args ),*) -> #return_type {
+                        #methods_ident::__client(self).#ident(#( #forward_args ),*)
+                    }
+                }
+            });
+        let supertrait_methods = supertraits.iter()
+            .map(|supertrait| {
+                let snake_case_ident = supertrait.snake_case_ident();
+                let methods_path = supertrait.generated("ClientMethods");
+                let client_path = supertrait.generated("Client");
+                quote! {
+                    impl #methods_path for #client_ident {
+                        fn __client(&self) -> &#client_path {
+                            &self.#snake_case_ident
+                        }
+                    }
+                }
+            });
+
         let rpc_fns = rpcs
             .iter()
             .zip(camel_case_idents.iter())
-            .map(|(RpcMethod { attrs, args, transfer, post, ident, output, .. }, camel_case_ident)| {
+            .map(|(rpc @ RpcMethod { attrs, args, transfer, post, remote, ident, output, is_ack, is_abortable, .. }, camel_case_ident)| {
                 /* sort arguments based on post and transfer attributes */
                 let serialize_arg_idents = args.iter()
                     .filter_map(|arg| match &*arg.pat {
                         Pat::Ident(ident) if !post.contains(&ident.ident) => Some(&ident.ident),
                         _ => None
                     });
-                let post_arg_idents = args.iter()
+                let collection_counts = args.iter()
+                    .filter_map(|arg| match &*arg.pat {
+                        Pat::Ident(ident) if rpc.posted_collection(&ident.ident, &arg.ty).is_some() => {
+                            let ident = &ident.ident;
+                            Some(quote!(#ident: #ident.iter().count()))
+                        },
+                        _ => None
+                    });
+                /* the posted arguments are pushed in order, posted collections push each of their values */
+                let push_post_args = args.iter()
                     .filter_map(|arg| match &*arg.pat {
-                        Pat::Ident(ident) if post.contains(&ident.ident) => Some(&ident.ident),
+                        Pat::Ident(ident) if post.contains(&ident.ident) => {
+                            let is_collection = rpc.posted_collection(&ident.ident, &arg.ty).is_some();
+                            let ident = &ident.ident;
+                            let value = match is_collection {
+                                true => quote!(__value),
+                                false => quote!(#ident),
+                            };
+                            let push_transfer = match transfer.contains(ident) {
+                                true => quote!(__transfer.push(#value.as_ref());),
+                                false => quote!(),
+                            };
+                            let push = quote! {
+                                __post.push(#value.as_ref());
+                                #push_transfer
+                            };
+                            Some(match is_collection {
+                                true => quote! {
+                                    for __value in #ident.iter() {
+                                        #push
+                                    }
+                                },
+                                false => push,
+                            })
+                        },
                         _ => None
                     });
-                let transfer_arg_idents = args.iter()
+                /* remote objects and callbacks are sent as the ports of their channels */
+                let remote_args_into_ports = args.iter()
                     .filter_map(|arg| match &*arg.pat {
-                        Pat::Ident(ident) if transfer.contains(&ident.ident) => Some(&ident.ident),
+                        Pat::Ident(ident) if remote.contains(&ident.ident) => {
+                            let ident = &ident.ident;
+                            let arg_ty = &arg.ty;
+                            Some(quote!(let #ident = <#arg_ty>::into_port(#ident);))
+                        },
                         _ => None
                     });
 
-                let return_type = match output {
-                    ReturnType::Type(_, ref ty) => quote! {
-                        web_rpc::client::RequestFuture<#ty>
-                    },
-                    _ => quote!(())
+                let return_type = client_return_type(rpc);
+                let return_ident = Ident::new("return", output.span());
+                let return_collection = match output {
+                    ReturnType::Type(_, ty) => rpc.posted_collection(&return_ident, ty),
+                    ReturnType::Default => None,
                 };
-                let maybe_register_callback = match output {
-                    ReturnType::Type(_, _) => quote! {
-                        let (__response_tx, __response_rx) =
-                            web_rpc::futures_channel::oneshot::channel();
-                        self.callback_map.borrow_mut().insert(__seq_id, __response_tx);
-                    },
-                    _ => Default::default()
+                /* the responses whose return values are posted cannot be returned by interceptors */
+                let request = match return_collection.is_some() || remote.contains(&return_ident)
+                    || post.contains(&return_ident) {
+                    true => quote!(request_posted),
+                    false => quote!(request),
                 };
-
-                let unpack_response = if post.contains(&Ident::new("return", output.span())) {
+                let unpack_response = if let Some((collection, element)) = return_collection {
+                    let values = collection.extract(krate, element, quote!(__count), quote!(__post_response));
+                    quote! {
+                        let #response_ident::#camel_case_ident(__count) = __serialize_response else {
+                            panic!("received incorrect response variant")
+                        };
+                        #values
+                    }
+                } else if remote.contains(&return_ident) {
+                    let ReturnType::Type(_, ref output) = output else {
+                        unreachable!("only return types can be remote")
+                    };
+                    quote! {
+                        <#output>::from_port(__post_response.shift())
+                    }
+                } else if post.contains(&return_ident) {
                     let unit_output: &Type = &parse_quote!(());
                     let output = match output {
                         ReturnType::Type(_, ref ty) => ty,
                         _ => unit_output
                     };
                     quote! {
-                        let (_, __post_response) = response;
-                        web_rpc::wasm_bindgen::JsCast::dyn_into::<#output>(__post_response.shift())
+                        #krate::wasm_bindgen::JsCast::dyn_into::<#output>(__post_response.shift())
                             .unwrap()
                     }
                 } else {
                     quote! {
-                        let (__serialize_response, _) = response;
                         let #response_ident::#camel_case_ident(__inner) = __serialize_response else {
                             panic!("received incorrect response variant")
                         };
@@ -242,23 +587,29 @@ impl<'a> ServiceGenerator<'a> {
                     }
                 };
 
-                let maybe_unpack_and_return_future = match output {
+                let method = ident.unraw().to_string();
+                let send = match output {
+                    /* acknowledged notifications are requests whose response is empty */
+                    _ if *is_ack => quote! {
+                        self.configuration.request(#method, __request, __post, __transfer, |_, _| ())
+                    },
+                    _ if *is_abortable => quote! {
+                        self.configuration.notify_abortable(#method, __request, __post, __transfer)
+                    },
                     ReturnType::Type(_, _) => quote! {
-                        let __response_future = web_rpc::futures_util::FutureExt::map(
-                            __response_rx,
-                            |response| {
-                                let response = response.unwrap();
+                        self.configuration.#request(
+                            #method,
+                            __request,
+                            __post,
+                            __transfer,
+                            |__serialize_response, __post_response| {
                                 #unpack_response
                             }
-                        );
-                        let __abort_sender = self.abort_sender.clone();
-                        let __dispatcher = self.dispatcher.clone();
-                        web_rpc::client::RequestFuture::new(
-                            __response_future,
-                            __dispatcher,
-                            std::boxed::Box::new(move || (__abort_sender)(__seq_id)))
+                        )
                     },
-                    _ => Default::default()
+                    _ => quote! {
+                        self.configuration.notify(#method, __request, __post, __transfer)
+                    }
                 };
 
                 quote! {
@@ -267,21 +618,15 @@ impl<'a> ServiceGenerator<'a> {
                         &self,
                         #( #args ),*
                     ) -> #return_type {
-                        let __seq_id = self.seq_id.replace_with(|seq_id| seq_id.wrapping_add(1));
                         let __request = #request_ident::#camel_case_ident {
-                            #( #serialize_arg_idents ),*
+                            #( #serialize_arg_idents, )*
+                            #( #collection_counts, )*
                         };
-                        let __serialized = (self.request_serializer)(__seq_id, __request);
-                        let __serialized = js_sys::Uint8Array::from(&__serialized[..]).buffer();
-                        let __post: &[&wasm_bindgen::JsValue] =
-                            &[__serialized.as_ref(), #( #post_arg_idents.as_ref() ),*];
-                        let __post = web_rpc::js_sys::Array::from_iter(__post);
-                        let __transfer: &[&wasm_bindgen::JsValue] =
-                            &[__serialized.as_ref(), #( #transfer_arg_idents.as_ref() ),*];
-                        let __transfer = web_rpc::js_sys::Array::from_iter(__transfer);
-                        #maybe_register_callback
-                        self.port.post_message(&__post, &__transfer).unwrap();
-                        #maybe_unpack_and_return_future
+                        #( #remote_args_into_ports )*
+                        let __post = #krate::js_sys::Array::new();
+                        let __transfer = #krate::js_sys::Array::new();
+                        #( #push_post_args )*
+                        #send
                     }
                 }
             });
@@ -289,21 +634,8 @@ impl<'a> ServiceGenerator<'a> {
         quote! {
             #[derive(core::clone::Clone)]
             #vis struct #client_ident {
-                callback_map: std::rc::Rc<
-                    std::cell::RefCell<
-                        web_rpc::client::CallbackMap<#response_ident>
-                    >
-                >,
-                port: web_rpc::port::Port,
-                listener: std::rc::Rc<web_rpc::gloo_events::EventListener>,
-                dispatcher: web_rpc::futures_util::future::Shared<
-                    web_rpc::futures_core::future::LocalBoxFuture<'static, ()>
-                >,
-                request_serializer: std::rc::Rc<
-                    dyn std::ops::Fn(usize, #request_ident) -> std::vec::Vec<u8>
-                >,
-                abort_sender: std::rc::Rc<dyn std::ops::Fn(usize)>,
-                seq_id: std::rc::Rc<std::cell::RefCell<usize>>
+                configuration: #krate::client::Configuration<#request_ident, #response_ident>,
+                #( #supertrait_idents: #supertrait_client_paths ),*
             }
             impl std::fmt::Debug for #client_ident {
                 fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -311,79 +643,254 @@ impl<'a> ServiceGenerator<'a> {
                         .finish()
                 }
             }
-            impl web_rpc::client::Client for #client_ident {
+            impl #krate::client::Client for #client_ident {
                 type Request = #request_ident;
                 type Response = #response_ident;
             }
-            impl From<web_rpc::client::Configuration<#request_ident, #response_ident>>
+            impl From<#krate::client::Configuration<#request_ident, #response_ident>>
                 for #client_ident {
-                fn from((callback_map, port, listener, dispatcher, request_serializer, abort_sender):
-                    web_rpc::client::Configuration<#request_ident, #response_ident>) -> Self {
+                fn from(configuration:
+                    #krate::client::Configuration<#request_ident, #response_ident>) -> Self {
                     Self {
-                        callback_map,
-                        port,
-                        listener,
-                        dispatcher,
-                        request_serializer,
-                        abort_sender,
-                        seq_id: std::default::Default::default()
+                        #( #supertrait_idents: #supertrait_clients, )*
+                        configuration,
                     }
                 }
             }
             impl #client_ident {
+                /// Get the current statistics of the client, these are shared with the clients
+                /// of the supertraits
+                #vis fn stats(&self) -> #krate::client::Stats {
+                    self.configuration.stats()
+                }
+                /// Create a client that sends `key` and `value` as metadata with its requests and
+                /// notifications in addition to the metadata of this client
+                #vis fn with_metadata(
+                    &self,
+                    key: impl std::convert::Into<std::string::String>,
+                    value: impl std::convert::Into<std::string::String>
+                ) -> Self {
+                    <Self as std::convert::From<_>>::from(self.configuration.with_metadata(key, value))
+                }
+                /// Create a client whose requests fail with `Error::Timeout` if their responses
+                /// are not received within `timeout`
+                #vis fn with_timeout(&self, timeout: std::time::Duration) -> Self {
+                    <Self as std::convert::From<_>>::from(self.configuration.with_timeout(timeout))
+                }
+                #( #supertrait_accessors )*
                 #( #rpc_fns )*
             }
+            #[doc = #methods_doc]
+            #vis trait #methods_ident {
+                #[doc(hidden)]
+                fn __client(&self) -> &#client_ident;
+                #( #method_fns )*
+            }
+            #( #supertrait_methods )*
         }
     }
 
     fn struct_server(&self) -> TokenStream2 {
         let &Self {
+            krate,
             vis,
             trait_ident,
             service_ident,
             request_ident,
             response_ident,
             camel_case_idents,
+            supertraits,
             rpcs,
+            is_actor,
+            has_context,
+            is_cooperative,
             ..
         } = self;
 
+        let context_arg = match has_context {
+            true => quote!(&__context,),
+            false => quote!(),
+        };
+        /* cooperative methods always need the context to get their cancellation token */
+        let get_cooperative_context = quote! {
+            let __context = #krate::RequestContext::current()
+                .expect("the context is set while the server executes a request");
+        };
+        let get_context = match has_context {
+            true => get_cooperative_context.clone(),
+            false => quote!(),
+        };
+
+        /* actors are wrapped in a RefCell, this is sound since the server will only execute
+           one request at a time for services that require exclusive access */
+        let borrow_server_impl = match is_actor {
+            true => quote!(let mut __server_impl = self.server_impl.borrow_mut();),
+            false => quote!(let __server_impl = &self.server_impl;),
+        };
+        let server_impl_ref = match is_actor {
+            true => quote!(&mut *__server_impl),
+            false => quote!(__server_impl),
+        };
+        let (server_impl_ty, server_impl_from, server_impl_param) = match is_actor {
+            true => (quote!(std::cell::RefCell<T>), quote!(std::cell::RefCell::new(server_impl)), quote!(&mut T)),
+            false => (quote!(T), quote!(server_impl), quote!(&T)),
+        };
+        let supertrait_handlers = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let service_path = supertrait.generated("Service");
+                quote! {
+                    #request_ident::#camel_case_ident(__request) => {
+                        let (_, __result) = #service_path::<T>::__execute(
+                            __server_impl, __seq_id, __abort_rx, __request, __js_args
+                        ).await;
+                        __result.map(|__result| __result.map(|(__response, __post, __transfer)|
+                            (#response_ident::#camel_case_ident(__response), __post, __transfer)))
+                    }
+                }
+            });
+        let supertrait_methods = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let service_path = supertrait.generated("Service");
+                quote! {
+                    Self::Request::#camel_case_ident(ref __request) =>
+                        <#service_path<T> as #krate::service::Service>::method(__request),
+                }
+            });
+        let methods = rpcs.iter()
+            .zip(camel_case_idents.iter())
+            .map(|(rpc, camel_case_ident)| {
+                let cfg_attrs = rpc.cfg_attrs();
+                let method = rpc.ident.unraw().to_string();
+                quote! {
+                    #( #cfg_attrs )*
+                    Self::Request::#camel_case_ident { .. } => #method,
+                }
+            });
+        let supertrait_method_names = supertraits.iter()
+            .map(|supertrait| {
+                let service_path = supertrait.generated("Service");
+                quote! {
+                    __methods.extend(<#service_path<T> as #krate::service::Service>::methods());
+                }
+            });
+        let method_names = rpcs.iter()
+            .map(|rpc| {
+                let cfg_attrs = rpc.cfg_attrs();
+                let method = rpc.ident.unraw().to_string();
+                quote! {
+                    #( #cfg_attrs )*
+                    __methods.push(#method);
+                }
+            });
+        let supertrait_cooperatives = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let service_path = supertrait.generated("Service");
+                quote! {
+                    Self::Request::#camel_case_ident(__request) =>
+                        <#service_path<T> as #krate::service::Service>::cooperative(__request),
+                }
+            });
+        let supertrait_order_keys = supertraits.iter()
+            .map(|supertrait| {
+                let camel_case_ident = supertrait.camel_case_ident();
+                let service_path = supertrait.generated("Service");
+                quote! {
+                    Self::Request::#camel_case_ident(__request) =>
+                        <#service_path<T> as #krate::service::Service>::order_key(__request),
+                }
+            });
+        let order_keys = rpcs.iter()
+            .zip(camel_case_idents.iter())
+            .filter_map(|(rpc, camel_case_ident)| {
+                let cfg_attrs = rpc.cfg_attrs();
+                rpc.order_key.as_ref().map(|order_key| quote! {
+                    #( #cfg_attrs )*
+                    Self::Request::#camel_case_ident { #order_key, .. } =>
+                        Some(#krate::service::hash_order_key(#order_key)),
+                })
+            });
         let handlers = rpcs.iter()
             .zip(camel_case_idents.iter())
-            .map(|(RpcMethod { is_async, ident, args, transfer, post, output, .. }, camel_case_ident)| {
+            .map(|(rpc @ RpcMethod { is_async, ident, args, transfer, post, remote, output, .. }, camel_case_ident)| {
+                let cfg_attrs = rpc.cfg_attrs();
+                /* the posted collections are destructured as the number of their values */
                 let serialize_arg_idents = args.iter()
                     .filter_map(|arg| match &*arg.pat {
                         Pat::Ident(ident) if !post.contains(&ident.ident) => Some(&ident.ident),
+                        Pat::Ident(ident) if rpc.posted_collection(&ident.ident, &arg.ty).is_some() => Some(&ident.ident),
                         _ => None
                     });
                 let extract_js_args = args.iter()
                     .filter_map(|arg| match &*arg.pat {
+                        Pat::Ident(ident) if rpc.posted_collection(&ident.ident, &arg.ty).is_some() => {
+                            let (collection, element) = rpc.posted_collection(&ident.ident, &arg.ty).unwrap();
+                            let arg_pat = &arg.pat;
+                            let arg_ty = &arg.ty;
+                            let count = &ident.ident;
+                            let values = collection.extract(krate, element, quote!(#count), quote!(__js_args));
+                            Some(quote! {
+                                let #arg_pat: #arg_ty = #values;
+                            })
+                        },
+                        Pat::Ident(ident) if remote.contains(&ident.ident) => {
+                            let arg_pat = &arg.pat;
+                            let arg_ty = &arg.ty;
+                            Some(quote! {
+                                let #arg_pat = <#arg_ty>::from_port(__js_args.shift());
+                            })
+                        },
                         Pat::Ident(ident) if post.contains(&ident.ident) => {
                             let arg_pat = &arg.pat;
                             let arg_ty = &arg.ty;
                             Some(quote! {
-                                let #arg_pat = web_rpc::wasm_bindgen::JsCast::dyn_into::<#arg_ty>(__js_args.shift())
+                                let #arg_pat = #krate::wasm_bindgen::JsCast::dyn_into::<#arg_ty>(__js_args.shift())
                                     .unwrap();
                             })
                         },
                         _ => None
                     });
                 let return_ident = Ident::new("return", output.span());
+                let into_port = match (remote.contains(&return_ident), output) {
+                    (true, ReturnType::Type(_, output)) =>
+                        quote!(let __response = <#output>::into_port(__response);),
+                    _ => quote!(),
+                };
+                let return_collection = match output {
+                    ReturnType::Type(_, ty) => rpc.posted_collection(&return_ident, ty),
+                    ReturnType::Default => None,
+                };
+                let push_transfer = match transfer.contains(&return_ident) {
+                    true => quote!(__transfer.push(__value.as_ref());),
+                    false => quote!(),
+                };
                 let return_response = match (post.contains(&return_ident), transfer.contains(&return_ident)) {
+                    (true, _) if return_collection.is_some() => quote! {
+                        let __post = #krate::js_sys::Array::new();
+                        let __transfer = #krate::js_sys::Array::new();
+                        for __value in __response.iter() {
+                            __post.push(__value.as_ref());
+                            #push_transfer
+                        }
+                        (#response_ident::#camel_case_ident(__post.length() as usize), __post, __transfer)
+                    },
                     (false, _) => quote! {
-                        let __post = web_rpc::js_sys::Array::new();
-                        let __transfer = web_rpc::js_sys::Array::new();
-                        (Self::Response::#camel_case_ident(__response), __post, __transfer)
+                        let __post = #krate::js_sys::Array::new();
+                        let __transfer = #krate::js_sys::Array::new();
+                        (#response_ident::#camel_case_ident(__response), __post, __transfer)
                     },
                     (true, false) => quote! {
-                        let __post = web_rpc::js_sys::Array::of1(__response.as_ref());
-                        let __transfer = web_rpc::js_sys::Array::new();
-                        (Self::Response::#camel_case_ident(()), __post, __transfer)
+                        let __post = #krate::js_sys::Array::of1(__response.as_ref());
+                        let __transfer = #krate::js_sys::Array::new();
+                        (#response_ident::#camel_case_ident(()), __post, __transfer)
                     },
                     (true, true) => quote! {
-                        let __post = web_rpc::js_sys::Array::of1(__response.as_ref());
-                        let __transfer = web_rpc::js_sys::Array::of1(__response.as_ref());
-                        (Self::Response::#camel_case_ident(()), __post, __transfer)
+                        #into_port
+                        let __post = #krate::js_sys::Array::of1(__response.as_ref());
+                        let __transfer = #krate::js_sys::Array::of1(__response.as_ref());
+                        (#response_ident::#camel_case_ident(()), __post, __transfer)
                     }
                 };
                 let args = args.iter().filter_map(|arg| match &*arg.pat {
@@ -391,27 +898,46 @@ impl<'a> ServiceGenerator<'a> {
                     _ => None
                 });
                 match is_async {
+                    /* cooperative methods are not dropped when their requests are cancelled */
+                    true if is_cooperative => quote! {
+                        #( #cfg_attrs )*
+                        #request_ident::#camel_case_ident { #( #serialize_arg_idents ),* } => {
+                            #( #extract_js_args )*
+                            #get_cooperative_context
+                            let __token = __context.token();
+                            let __response =
+                                __server_impl.#ident(#context_arg &__token, #( #args ),*).await;
+                            Some(Ok({
+                                #return_response
+                            }))
+                        }
+                    },
                     true => quote! {
-                        Self::Request::#camel_case_ident { #( #serialize_arg_idents ),* } => {
+                        #( #cfg_attrs )*
+                        #request_ident::#camel_case_ident { #( #serialize_arg_idents ),* } => {
                             #( #extract_js_args )*
-                            let __task =
-                                web_rpc::futures_util::FutureExt::fuse(self.server_impl.#ident(#( #args ),*));
-                            web_rpc::pin_utils::pin_mut!(__task);
-                            web_rpc::futures_util::select! {
+                            #get_context
+                            let __task = #krate::futures_util::FutureExt::fuse(
+                                __server_impl.#ident(#context_arg #( #args ),*)
+                            );
+                            #krate::pin_utils::pin_mut!(__task);
+                            #krate::futures_util::select! {
                                 _ = __abort_rx => None,
-                                __response = __task => Some({
+                                __response = __task => Some(Ok({
                                     #return_response
-                                })
+                                }))
                             }
                         }
                     },
                     false => quote! {
-                        Self::Request::#camel_case_ident { #( #serialize_arg_idents ),* } => {
+                        #( #cfg_attrs )*
+                        #request_ident::#camel_case_ident { #( #serialize_arg_idents ),* } => {
                             #( #extract_js_args )*
-                            let __response = self.server_impl.#ident(#( #args ),*);
-                            Some({
+                            #get_context
+                            let __response = __server_impl.#ident(#context_arg #( #args ),*);
+                            Some(Ok({
                                 #return_response
-                            })
+                            }))
                         }
                     }
                 }
@@ -419,19 +945,65 @@ impl<'a> ServiceGenerator<'a> {
 
         quote! {
             #vis struct #service_ident<T> {
-                server_impl: T
+                server_impl: #server_impl_ty
             }
-            impl<T: #trait_ident> web_rpc::service::Service for #service_ident<T> {
+            impl<T: #trait_ident> #krate::service::Service for #service_ident<T> {
                 type Request = #request_ident;
                 type Response = #response_ident;
+                const EXCLUSIVE: bool = #is_actor;
+                fn method(__request: &Self::Request) -> &'static str {
+                    match *__request {
+                        #( #supertrait_methods )*
+                        #( #methods )*
+                    }
+                }
+                fn methods() -> Vec<&'static str> {
+                    #[allow(unused_mut)]
+                    let mut __methods = Vec::new();
+                    #( #supertrait_method_names )*
+                    #( #method_names )*
+                    __methods
+                }
+                fn order_key(__request: &Self::Request) -> Option<u64> {
+                    match __request {
+                        #( #supertrait_order_keys )*
+                        #( #order_keys )*
+                        #[allow(unreachable_patterns)]
+                        _ => None
+                    }
+                }
+                fn cooperative(__request: &Self::Request) -> bool {
+                    match __request {
+                        #( #supertrait_cooperatives )*
+                        #[allow(unreachable_patterns)]
+                        _ => #is_cooperative
+                    }
+                }
+                #[allow(clippy::await_holding_refcell_ref)]
                 async fn execute(
                     &self,
                     __seq_id: usize,
-                    mut __abort_rx: web_rpc::futures_channel::oneshot::Receiver<()>,
+                    __abort_rx: #krate::futures_channel::oneshot::Receiver<()>,
                     __request: Self::Request,
-                    __js_args: web_rpc::js_sys::Array
-                ) -> (usize, Option<(Self::Response, web_rpc::js_sys::Array, web_rpc::js_sys::Array)>) {
+                    __js_args: #krate::js_sys::Array
+                ) -> (usize, #krate::service::ExecuteResult<Self::Response>) {
+                    #borrow_server_impl
+                    Self::__execute(#server_impl_ref, __seq_id, __abort_rx, __request, __js_args).await
+                }
+            }
+            impl<T: #trait_ident> #service_ident<T> {
+                /// Execute a request on a reference to the implementation, this is also used by the
+                /// services that have this service's trait as a supertrait
+                #[doc(hidden)]
+                #vis async fn __execute(
+                    __server_impl: #server_impl_param,
+                    __seq_id: usize,
+                    mut __abort_rx: #krate::futures_channel::oneshot::Receiver<()>,
+                    __request: #request_ident,
+                    __js_args: #krate::js_sys::Array
+                ) -> (usize, #krate::service::ExecuteResult<#response_ident>) {
                     let __result = match __request {
+                        #( #supertrait_handlers )*
                         #( #handlers )*
                     };
                     (__seq_id, __result)
@@ -439,7 +1011,7 @@ impl<'a> ServiceGenerator<'a> {
             }
             impl<T: #trait_ident> std::convert::From<T> for #service_ident<T> {
                 fn from(server_impl: T) -> Self {
-                    Self { server_impl }
+                    Self { server_impl: #server_impl_from }
                 }
             }
         }
@@ -458,12 +1030,101 @@ impl<'a> ToTokens for ServiceGenerator<'a> {
     }
 }
 
+impl ServiceArgs {
+    fn parse(args: AttributeArgs) -> syn::Result<Self> {
+        let mut errors = Ok(());
+        let mut service_args = ServiceArgs {
+            client_ident: None,
+            service_ident: None,
+            request_ident: None,
+            response_ident: None,
+            derives: Vec::new(),
+            krate: parse_quote!(web_rpc),
+            is_actor: false,
+            has_context: false,
+            is_cooperative: false,
+        };
+        for arg in args {
+            match &arg {
+                NestedMeta::Meta(Meta::NameValue(name_value)) => {
+                    let Some(name) = name_value.path.get_ident().map(ToString::to_string) else {
+                        extend_errors!(errors, syn::Error::new(arg.span(), "Syntax error in service attribute"));
+                        continue;
+                    };
+                    let Lit::Str(value) = &name_value.lit else {
+                        extend_errors!(errors, syn::Error::new(name_value.lit.span(), "Expected a string literal"));
+                        continue;
+                    };
+                    match name.as_str() {
+                        "crate" => match value.parse() {
+                            Ok(path) => service_args.krate = path,
+                            Err(error) => extend_errors!(errors, error),
+                        },
+                        "client" | "service" | "request" | "response" => {
+                            let ident = match value.parse::<Ident>() {
+                                Ok(ident) => ident,
+                                Err(error) => {
+                                    extend_errors!(errors, error);
+                                    continue;
+                                }
+                            };
+                            let target = match name.as_str() {
+                                "client" => &mut service_args.client_ident,
+                                "service" => &mut service_args.service_ident,
+                                "request" => &mut service_args.request_ident,
+                                _ => &mut service_args.response_ident,
+                            };
+                            if target.replace(ident).is_some() {
+                                extend_errors!(errors, syn::Error::new(arg.span(), format!("`{name}` specified more than once")));
+                            }
+                        },
+                        _ => extend_errors!(errors, syn::Error::new(arg.span(), format!("Unknown service argument `{name}`"))),
+                    }
+                },
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("actor") =>
+                    service_args.is_actor = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("context") =>
+                    service_args.has_context = true,
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("cooperative") =>
+                    service_args.is_cooperative = true,
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("derive") => {
+                    for nested in &list.nested {
+                        match nested {
+                            NestedMeta::Meta(Meta::Path(path)) => service_args.derives.push(path.clone()),
+                            _ => extend_errors!(errors, syn::Error::new(nested.span(), "Expected a path to a derive macro")),
+                        }
+                    }
+                },
+                _ => extend_errors!(errors, syn::Error::new(arg.span(), "Syntax error in service attribute")),
+            }
+        }
+        errors.map(|_| service_args)
+    }
+}
+
 impl Parse for Service {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let attrs = input.call(Attribute::parse_outer)?;
         let vis = input.parse()?;
         input.parse::<Token![trait]>()?;
         let ident: Ident = input.parse()?;
+        let mut supertraits = Vec::new();
+        if input.parse::<Token![:]>().is_ok() {
+            let mut errors = Ok(());
+            let bounds = Punctuated::<TypeParamBound, Token![+]>::parse_separated_nonempty(input)?;
+            for bound in bounds {
+                match bound {
+                    TypeParamBound::Trait(TraitBound { modifier: TraitBoundModifier::None, lifetimes: None, path, .. })
+                        if path.segments.iter().all(|segment| segment.arguments.is_empty()) =>
+                            supertraits.push(Supertrait { path }),
+                    _ => extend_errors!(
+                        errors,
+                        syn::Error::new(bound.span(), "supertraits must be services without generic arguments")
+                    )
+                }
+            }
+            errors?;
+        }
         let content;
         braced!(content in input);
         let mut rpcs = Vec::<RpcMethod>::new();
@@ -475,6 +1136,7 @@ impl Parse for Service {
             attrs,
             vis,
             ident,
+            supertraits,
             rpcs,
         })
     }
@@ -487,6 +1149,18 @@ impl Parse for RpcMethod {
         let (post_attrs, attrs): (Vec<_>, Vec<_>) = attrs.into_iter()
             .partition(|attr| attr.path.segments.last()
                 .is_some_and(|last_segment| last_segment.ident == "post"));
+        let (ack_attrs, attrs): (Vec<_>, Vec<_>) = attrs.into_iter()
+            .partition(|attr| attr.path.is_ident("ack"));
+        let (abortable_attrs, attrs): (Vec<_>, Vec<_>) = attrs.into_iter()
+            .partition(|attr| attr.path.is_ident("abortable"));
+        let (remote_attrs, attrs): (Vec<_>, Vec<_>) = attrs.into_iter()
+            .partition(|attr| attr.path.is_ident("remote"));
+        if let (Some(_), Some(abortable_attr)) = (ack_attrs.first(), abortable_attrs.first()) {
+            extend_errors!(
+                errors,
+                syn::Error::new(abortable_attr.span(), "a notification cannot be both acknowledged and abortable")
+            );
+        }
         let mut transfer: HashSet<Ident> = HashSet::new();
         let mut post: HashSet<Ident> = HashSet::new();
         for post_attr in post_attrs {
@@ -540,18 +1214,58 @@ impl Parse for RpcMethod {
                 }
             }
         }
-        
+        /* remote objects and callbacks are posted and transferred as the ports of their channels */
+        let mut remote: HashSet<Ident> = HashSet::new();
+        for remote_attr in remote_attrs {
+            let parsed_args =
+                remote_attr.parse_args_with(Punctuated::<NestedMeta, Token![,]>::parse_terminated)?;
+            for parsed_arg in parsed_args {
+                match &parsed_arg {
+                    NestedMeta::Meta(Meta::Path(path)) if path.segments.len() == 1 => {
+                        let ident = &path.segments[0].ident;
+                        remote.insert(ident.clone());
+                        post.insert(ident.clone());
+                        transfer.insert(ident.clone());
+                    },
+                    _ => extend_errors!(
+                        errors,
+                        syn::Error::new(parsed_arg.span(), "Syntax error in remote attribute")
+                    )
+                }
+            }
+        }
+
         let is_async = input.parse::<Token![async]>().is_ok();
         input.parse::<Token![fn]>()?;
         let ident = input.parse()?;
         let content;
         parenthesized!(content in input);
         let mut args = Vec::new();
+        let mut order_key = None;
         for arg in content.parse_terminated::<FnArg, Comma>(FnArg::parse)? {
             match arg {
-                FnArg::Typed(captured) => {
+                FnArg::Typed(mut captured) => {
+                    let (order_key_attrs, attrs): (Vec<_>, Vec<_>) = captured.attrs.into_iter()
+                        .partition(|attr| attr.path.is_ident("order_key"));
+                    captured.attrs = attrs;
                     match &*captured.pat {
-                        Pat::Ident(_) => args.push(captured),
+                        Pat::Ident(pat_ident) => {
+                            if let Some(order_key_attr) = order_key_attrs.first() {
+                                if order_key.is_some() {
+                                    extend_errors!(
+                                        errors,
+                                        syn::Error::new(order_key_attr.span(), "only one argument can be the order key")
+                                    )
+                                } else if post.contains(&pat_ident.ident) {
+                                    extend_errors!(
+                                        errors,
+                                        syn::Error::new(order_key_attr.span(), "posted arguments cannot be the order key")
+                                    )
+                                }
+                                order_key = Some(pat_ident.ident.clone());
+                            }
+                            args.push(captured)
+                        },
                         _ => {
                             extend_errors!(
                                 errors,
@@ -568,17 +1282,46 @@ impl Parse for RpcMethod {
                 }
             }
         }
+        let output: ReturnType = input.parse()?;
+        for ident in &remote {
+            let is_arg = args.iter().any(|arg| matches!(&*arg.pat, Pat::Ident(pat_ident) if pat_ident.ident == *ident));
+            let is_return = ident == "return" && matches!(output, ReturnType::Type(..));
+            if !is_arg && !is_return {
+                extend_errors!(
+                    errors,
+                    syn::Error::new(ident.span(), format!("`{ident}` is neither an argument nor the return value"))
+                );
+            }
+        }
+        if let ReturnType::Type(..) = output {
+            for attr in ack_attrs.iter().chain(abortable_attrs.iter()) {
+                extend_errors!(
+                    errors,
+                    syn::Error::new(attr.span(), "only methods without a return type can be acknowledged or abortable")
+                );
+            }
+        }
+        /* synchronous methods complete before an abort could reach the server */
+        if let (false, Some(abortable_attr)) = (is_async, abortable_attrs.first()) {
+            extend_errors!(
+                errors,
+                syn::Error::new(abortable_attr.span(), "only async methods can be abortable")
+            );
+        }
         errors?;
-        let output = input.parse()?;
         input.parse::<Token![;]>()?;
 
         Ok(Self {
             is_async,
+            is_ack: !ack_attrs.is_empty(),
+            is_abortable: !abortable_attrs.is_empty(),
             attrs,
             ident,
             args,
             post,
             transfer,
+            remote,
+            order_key,
             output,
         })
     }
@@ -589,15 +1332,100 @@ impl Parse for RpcMethod {
 /// a trait `Calculator` will be replaced with two structs `CalculatorClient` and
 /// `CalculatorService` and a new trait by the same name with the methods which have had
 /// the a `&self` receiver added to them.
+///
+/// The generated items can be configured with the following arguments:
+/// - `client = "..."`, `service = "..."`, `request = "..."`, and `response = "..."` override
+///   the names of the generated client, service, request, and response types. These names
+///   must not be overridden for services that are used as supertraits.
+/// - `derive(...)` adds derives (e.g., `Debug` or `Clone`) to the request and response types.
+/// - `crate = "..."` sets the path to the `web_rpc` crate for when it is re-exported from
+///   another crate.
+/// - `actor` makes the methods of the trait take `&mut self`, in which case the server executes
+///   the requests one at a time.
+/// - `context` passes the `RequestContext` of the request as the first parameter, named `ctx`,
+///   to the methods of the trait.
+/// - `cooperative` passes a `CancellationToken`, named `token`, after the context to the async
+///   methods of the trait, which are then not dropped when their requests are cancelled.
 #[proc_macro_attribute]
-pub fn service(_attr: TokenStream, input: TokenStream) -> TokenStream {
+pub fn service(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let service_args = parse_macro_input!(attr as AttributeArgs);
+    let ServiceArgs {
+        ref client_ident,
+        ref service_ident,
+        ref request_ident,
+        ref response_ident,
+        ref derives,
+        ref krate,
+        is_actor,
+        has_context,
+        is_cooperative,
+    } = match ServiceArgs::parse(service_args) {
+        Ok(service_args) => service_args,
+        Err(error) => return error.to_compile_error().into(),
+    };
     let Service {
         ref attrs,
         ref vis,
         ref ident,
+        ref supertraits,
         ref rpcs,
     } = parse_macro_input!(input as Service);
 
+    /* the variants for the supertraits and the methods share the request and response enums */
+    let mut variants = HashSet::new();
+    let mut errors = Ok(());
+    for (span, variant) in supertraits.iter()
+        .map(|supertrait| (supertrait.path.span(), supertrait.camel_case_ident().to_string()))
+        .chain(rpcs.iter().map(|rpc| (rpc.ident.span(), snake_to_camel(&rpc.ident.unraw().to_string())))) {
+        if !variants.insert(variant.clone()) {
+            extend_errors!(
+                errors,
+                syn::Error::new(span, format!("the variant `{variant}` is generated more than once"))
+            );
+        }
+    }
+    /* the client's methods, the supertrait accessors, `stats`, `with_metadata`, and `with_timeout`
+       share the client's namespace */
+    let mut client_fns = ["stats", "with_metadata", "with_timeout"].into_iter()
+        .map(String::from)
+        .collect::<HashSet<_>>();
+    for (span, client_fn) in supertraits.iter()
+        .map(|supertrait| (supertrait.path.span(), supertrait.snake_case_ident().to_string()))
+        .chain(rpcs.iter().map(|rpc| (rpc.ident.span(), rpc.ident.unraw().to_string()))) {
+        if !client_fns.insert(client_fn.clone()) {
+            let message = match client_fn.as_str() {
+                "stats" => String::from("`stats` is reserved for the statistics of the client"),
+                "with_metadata" => String::from("`with_metadata` is reserved for the metadata of the client"),
+                "with_timeout" => String::from("`with_timeout` is reserved for the timeout of the client"),
+                _ => format!("the client method `{client_fn}` is generated more than once"),
+            };
+            extend_errors!(errors, syn::Error::new(span, message));
+        }
+    }
+    /* the context and the cancellation token are passed to the methods as `ctx` and `token`,
+       which must not clash with their arguments */
+    for rpc in rpcs {
+        for arg in &rpc.args {
+            if let Pat::Ident(pat_ident) = &*arg.pat {
+                if has_context && pat_ident.ident == "ctx" {
+                    extend_errors!(
+                        errors,
+                        syn::Error::new(pat_ident.span(), "`ctx` is reserved for the context of services with the `context` argument")
+                    );
+                }
+                if is_cooperative && rpc.is_async && pat_ident.ident == "token" {
+                    extend_errors!(
+                        errors,
+                        syn::Error::new(pat_ident.span(), "`token` is reserved for the cancellation token of services with the `cooperative` argument")
+                    );
+                }
+            }
+        }
+    }
+    if let Err(errors) = errors {
+        return errors.to_compile_error().into();
+    }
+
     let camel_case_fn_names: &Vec<_> = &rpcs
         .iter()
         .map(|rpc| snake_to_camel(&rpc.ident.unraw().to_string()))
@@ -606,23 +1434,218 @@ pub fn service(_attr: TokenStream, input: TokenStream) -> TokenStream {
 
     ServiceGenerator {
         trait_ident: ident,
-        service_ident: &format_ident!("{}Service", ident),
-        client_ident: &format_ident!("{}Client", ident),
-        request_ident: &format_ident!("{}Request", ident),
-        response_ident: &format_ident!("{}Response", ident),
+        service_ident: &service_ident.clone()
+            .unwrap_or_else(|| format_ident!("{}Service", ident)),
+        client_ident: &client_ident.clone()
+            .unwrap_or_else(|| format_ident!("{}Client", ident)),
+        request_ident: &request_ident.clone()
+            .unwrap_or_else(|| format_ident!("{}Request", ident)),
+        response_ident: &response_ident.clone()
+            .unwrap_or_else(|| format_ident!("{}Response", ident)),
         vis,
         attrs,
+        supertraits,
         rpcs,
         camel_case_idents: &rpcs
             .iter()
             .zip(camel_case_fn_names.iter())
             .map(|(rpc, name)| Ident::new(name, rpc.ident.span()))
             .collect::<Vec<_>>(),
+        derives,
+        krate,
+        is_actor,
+        has_context,
+        is_cooperative,
     }
     .into_token_stream()
     .into()
 }
 
+/// A field of a struct that derives `Postable`
+struct PostableField<'a> {
+    ident: &'a Ident,
+    ty: &'a Type,
+    /* the attributes that are copied onto the serialized field, e.g., `serde` attributes */
+    attrs: Vec<&'a Attribute>,
+    /* `None` if the field is serialized, otherwise whether the posted field is transferred */
+    post: Option<bool>,
+}
+
+/// Parse the `post` attribute of a field, i.e., `#[post]` or `#[post(transfer)]`
+fn parse_post_field(attr: &Attribute) -> syn::Result<bool> {
+    match attr.parse_meta()? {
+        Meta::Path(_) => Ok(false),
+        Meta::List(list) if list.nested.len() == 1 => match list.nested.first() {
+            Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("transfer") => Ok(true),
+            _ => Err(syn::Error::new(list.span(), "expected `#[post]` or `#[post(transfer)]`")),
+        },
+        meta => Err(syn::Error::new(meta.span(), "expected `#[post]` or `#[post(transfer)]`")),
+    }
+}
+
+/// Parse the `post` attribute of a struct, i.e., `#[post(crate = "...")]`
+fn parse_post_container(attr: &Attribute) -> syn::Result<Path> {
+    match attr.parse_meta()? {
+        Meta::List(list) if list.nested.len() == 1 => match list.nested.first() {
+            Some(NestedMeta::Meta(Meta::NameValue(name_value))) if name_value.path.is_ident("crate") =>
+                match &name_value.lit {
+                    Lit::Str(value) => value.parse(),
+                    lit => Err(syn::Error::new(lit.span(), "Expected a string literal")),
+                },
+            _ => Err(syn::Error::new(list.span(), "expected `#[post(crate = \"...\")]`")),
+        },
+        meta => Err(syn::Error::new(meta.span(), "expected `#[post(crate = \"...\")]`")),
+    }
+}
+
+fn derive_postable(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut errors = Ok(());
+    let mut krate: Path = parse_quote!(web_rpc);
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("post")) {
+        match parse_post_container(attr) {
+            Ok(path) => krate = path,
+            Err(error) => extend_errors!(errors, error),
+        }
+    }
+    if !input.generics.params.is_empty() {
+        extend_errors!(errors, syn::Error::new(input.generics.span(), "Postable cannot be derived for generic types"));
+    }
+    let Data::Struct(DataStruct { fields: Fields::Named(fields), .. }) = &input.data else {
+        extend_errors!(errors, syn::Error::new(input.span(), "Postable can only be derived for structs with named fields"));
+        return errors.map(|_| TokenStream2::new());
+    };
+    let mut postable_fields = Vec::new();
+    for field in &fields.named {
+        let mut post = None;
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("post")) {
+            match parse_post_field(attr) {
+                Ok(transfer) => post = Some(transfer),
+                Err(error) => extend_errors!(errors, error),
+            }
+        }
+        postable_fields.push(PostableField {
+            ident: field.ident.as_ref().unwrap(),
+            ty: &field.ty,
+            attrs: field.attrs.iter().filter(|attr| attr.path.is_ident("serde")).collect(),
+            post,
+        });
+    }
+    errors?;
+
+    let ident = &input.ident;
+    let serde_crate = serde_crate(&krate);
+    let (serialized, posted): (Vec<_>, Vec<_>) = postable_fields.iter()
+        .partition(|field| field.post.is_none());
+    let serialized_fields = serialized.iter()
+        .map(|PostableField { ident, ty, attrs, .. }| quote!(#( #attrs )* #ident: &'__a #ty))
+        .collect::<Vec<_>>();
+    let deserialized_fields = serialized.iter()
+        .map(|PostableField { ident, ty, attrs, .. }| quote!(#( #attrs )* #ident: #ty));
+    let serialized_idents = serialized.iter()
+        .map(|field| field.ident)
+        .collect::<Vec<_>>();
+    let posted_idents = posted.iter()
+        .map(|field| field.ident)
+        .collect::<Vec<_>>();
+    let posted_types = posted.iter()
+        .map(|field| field.ty);
+    let posted_transfers = posted.iter()
+        .map(|field| field.post.unwrap_or_default());
+    Ok(quote! {
+        const _: () = {
+            #[derive(#krate::serde::Serialize)]
+            #[serde(crate = #serde_crate)]
+            struct __Serialized<'__a> {
+                #( #serialized_fields, )*
+                #[serde(skip)]
+                __lifetime: std::marker::PhantomData<&'__a ()>,
+            }
+            #[derive(#krate::serde::Deserialize)]
+            #[serde(crate = #serde_crate)]
+            struct __Deserialized {
+                #( #deserialized_fields, )*
+            }
+            impl #krate::serde::Serialize for #ident {
+                fn serialize<__S: #krate::serde::Serializer>(&self, __serializer: __S)
+                    -> std::result::Result<__S::Ok, __S::Error> {
+                    let __serialized = __Serialized {
+                        #( #serialized_idents: &self.#serialized_idents, )*
+                        __lifetime: std::marker::PhantomData,
+                    };
+                    /* the posted fields follow the values posted by the serialized fields */
+                    let __result = #krate::serde::Serialize::serialize(&__serialized, __serializer);
+                    #(
+                        #krate::post::post(
+                            std::convert::AsRef::<#krate::wasm_bindgen::JsValue>::as_ref(&self.#posted_idents),
+                            #posted_transfers
+                        ).map_err(<__S::Error as #krate::serde::ser::Error>::custom)?;
+                    )*
+                    __result
+                }
+            }
+            impl<'__de> #krate::serde::Deserialize<'__de> for #ident {
+                fn deserialize<__D: #krate::serde::Deserializer<'__de>>(__deserializer: __D)
+                    -> std::result::Result<Self, __D::Error> {
+                    let __Deserialized { #( #serialized_idents, )* } =
+                        <__Deserialized as #krate::serde::Deserialize>::deserialize(__deserializer)?;
+                    #(
+                        let #posted_idents = #krate::post::take::<#posted_types>()
+                            .map_err(<__D::Error as #krate::serde::de::Error>::custom)?;
+                    )*
+                    std::result::Result::Ok(Self {
+                        #( #serialized_idents, )*
+                        #( #posted_idents, )*
+                    })
+                }
+            }
+        };
+    })
+}
+
+/// This derive macro implements `Serialize` and `Deserialize` for structs that mix serialized
+/// fields with Javascript values that are posted alongside the message, for example:
+/// ```rust
+/// #[derive(web_rpc::Postable)]
+/// pub struct Frame {
+///     width: u32,
+///     height: u32,
+///     #[post(transfer)]
+///     bitmap: web_sys::ImageBitmap,
+/// }
+/// ```
+/// Fields annotated with `#[post]` are posted and fields annotated with `#[post(transfer)]` are
+/// also transferred, while the remaining fields are serialized. Such structs can be used as
+/// arguments and return values of RPC methods without the `post` attribute, including inside
+/// other serialized types such as `Option` or `Vec`. Note that the posted fields can only be
+/// serialized while a message is being sent. The path to the `web_rpc` crate can be set with
+/// `#[post(crate = "...")]` on the struct.
+#[proc_macro_derive(Postable, attributes(post))]
+pub fn postable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_postable(&input)
+        .unwrap_or_else(|errors| errors.to_compile_error())
+        .into()
+}
+
+/// The path to the serde crate re-exported by `web_rpc` as a string for use in
+/// `#[serde(crate = "...")]`
+fn serde_crate(krate: &Path) -> String {
+    quote!(#krate::serde).to_string()
+}
+
+fn camel_to_snake(ident_str: &str) -> String {
+    let mut snake_ty = String::with_capacity(ident_str.len() + 4);
+
+    for (index, c) in ident_str.chars().enumerate() {
+        if c.is_uppercase() && index != 0 {
+            snake_ty.push('_');
+        }
+        snake_ty.extend(c.to_lowercase());
+    }
+
+    snake_ty
+}
+
 fn snake_to_camel(ident_str: &str) -> String {
     let mut camel_ty = String::with_capacity(ident_str.len());
 
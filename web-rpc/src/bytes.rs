@@ -0,0 +1,117 @@
+use js_sys::{ArrayBuffer, Uint8Array};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// `Bytes` is a byte buffer that is transferred as its own `ArrayBuffer` instead of being
+/// serialized. Bytes that were created from an `ArrayBuffer` or that were received are not
+/// copied when they are sent, while bytes that were created from a `Vec<u8>` are copied once
+/// into a new `ArrayBuffer`. Received bytes remain in Javascript memory until they are copied
+/// into Rust using [`Bytes::to_vec`] or [`Bytes::into_vec`].
+///
+/// `Bytes` implements `Serialize` and `Deserialize` and can be used as an argument, as a return
+/// value, or inside other serialized types. Note that bytes can only be serialized while a
+/// message is being sent.
+pub struct Bytes {
+    inner: Inner,
+}
+
+enum Inner {
+    Vec(Vec<u8>),
+    Buffer(ArrayBuffer),
+}
+
+impl Bytes {
+    /// The number of bytes
+    pub fn len(&self) -> usize {
+        match &self.inner {
+            Inner::Vec(bytes) => bytes.len(),
+            Inner::Buffer(buffer) => buffer.byte_length() as usize,
+        }
+    }
+
+    /// Check whether there are no bytes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Copy the bytes into a `Vec<u8>`
+    pub fn to_vec(&self) -> Vec<u8> {
+        match &self.inner {
+            Inner::Vec(bytes) => bytes.clone(),
+            Inner::Buffer(buffer) => Uint8Array::new(buffer).to_vec(),
+        }
+    }
+
+    /// Convert the bytes into a `Vec<u8>`, this only copies the bytes if they are in
+    /// Javascript memory
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.inner {
+            Inner::Vec(bytes) => bytes,
+            Inner::Buffer(buffer) => Uint8Array::new(&buffer).to_vec(),
+        }
+    }
+
+    /// Convert the bytes into an `ArrayBuffer`, this only copies the bytes if they are in
+    /// Rust memory
+    pub fn into_array_buffer(self) -> ArrayBuffer {
+        match self.inner {
+            Inner::Vec(bytes) => Uint8Array::from(&bytes[..]).buffer(),
+            Inner::Buffer(buffer) => buffer,
+        }
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { inner: Inner::Vec(bytes) }
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Self { inner: Inner::Vec(bytes.to_vec()) }
+    }
+}
+
+impl From<ArrayBuffer> for Bytes {
+    fn from(buffer: ArrayBuffer) -> Self {
+        Self { inner: Inner::Buffer(buffer) }
+    }
+}
+
+impl From<Uint8Array> for Bytes {
+    /// The buffer of the array is used if the array covers all of it and is copied otherwise
+    fn from(array: Uint8Array) -> Self {
+        let buffer = array.buffer();
+        match array.byte_offset() == 0 && array.byte_length() == buffer.byte_length() {
+            true => buffer.into(),
+            false => array.slice(0, array.length()).buffer().into(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Bytes {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.debug_struct("Bytes")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        /* the buffer is posted and only its presence is serialized */
+        crate::post::post_with(true, || match &self.inner {
+            Inner::Vec(bytes) => Uint8Array::from(&bytes[..]).buffer().into(),
+            Inner::Buffer(buffer) => buffer.clone().into(),
+        }).map_err(S::Error::custom)?;
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <()>::deserialize(deserializer)?;
+        let buffer = crate::post::take::<ArrayBuffer>().map_err(D::Error::custom)?;
+        Ok(buffer.into())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+
+use js_sys::{Array, ArrayBuffer};
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::remote::{is_release, release, Release};
+
+/// `Callback` is a closure that can be passed to the methods of a service. The closure stays
+/// in the calling context while the callback that is received by the service posts the values
+/// that it is called with back to the closure over a channel of its own. This allows a service
+/// to push events to its caller without a second service and client in the reverse direction:
+/// ```rust
+/// #[web_rpc::service]
+/// pub trait Watcher {
+///     #[remote(on_change)]
+///     fn watch(path: String, on_change: web_rpc::Callback<ChangeEvent>);
+/// }
+/// client.watch(path, web_rpc::Callback::new(|event: ChangeEvent| {
+///     /* handle the event */
+/// }));
+/// ```
+/// Like remotes, callbacks must be listed in the `remote` attribute of their method. The values
+/// are serialized, i.e., `T` must implement [`Serialize`] and [`DeserializeOwned`].
+/// The closure is dropped once the received callback has been dropped or if the callback is
+/// dropped without being sent.
+pub struct Callback<T> {
+    /* the port of the channel before the callback is sent */
+    port: Option<web_sys::MessagePort>,
+    /* the port to which the values are posted once the callback has been received */
+    sender: Option<Release>,
+    value: PhantomData<fn(T)>,
+}
+
+impl<T: DeserializeOwned + 'static> Callback<T> {
+    /// Create a callback that runs `closure` for each value that the callback is called with
+    pub fn new(closure: impl Fn(T) + 'static) -> Self {
+        let channel = web_sys::MessageChannel::new().unwrap();
+        let receiver_port = channel.port1();
+        /* the listener keeps itself alive until the callback has been released */
+        let listener: Rc<RefCell<Option<gloo_events::EventListener>>> = Default::default();
+        let listener_cloned = listener.clone();
+        listener.replace(Some(gloo_events::EventListener::new(&receiver_port, "message", move |event| {
+            let message = event.unchecked_ref::<web_sys::MessageEvent>().data();
+            if is_release(&message) {
+                /* the listener cannot be dropped while it is running */
+                let listener = listener_cloned.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    listener.take();
+                });
+            } else if let Ok(array) = message.dyn_into::<Array>() {
+                let buffer = array.shift().unchecked_into::<ArrayBuffer>();
+                closure(crate::codec::decode(&buffer, &array).unwrap());
+            }
+        })));
+        receiver_port.start();
+        Self {
+            port: Some(channel.port2()),
+            sender: None,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize> Callback<T> {
+    /// Call the closure of the callback with `value`. This method panics if the callback has
+    /// not been received.
+    pub fn call(&self, value: T) {
+        let sender = self.sender.as_ref()
+            .expect("only callbacks that have been received can be called");
+        let (buffer, posted, transferred) = crate::codec::encode(&value);
+        posted.unshift(&buffer);
+        transferred.unshift(&buffer);
+        let _ = sender.port().post_message_with_transferable(&posted, &transferred);
+    }
+}
+
+impl<T> Callback<T> {
+    #[doc(hidden)]
+    pub fn into_port(mut self) -> web_sys::MessagePort {
+        self.port.take()
+            .expect("only callbacks that have been created using `Callback::new` can be sent")
+    }
+
+    #[doc(hidden)]
+    pub fn from_port(port: JsValue) -> Self {
+        let port = port.dyn_into::<web_sys::MessagePort>().unwrap();
+        Self {
+            port: None,
+            sender: Some(Release::new(port)),
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Callback<T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.debug_struct("Callback")
+            .field("received", &self.sender.is_some())
+            .finish()
+    }
+}
+
+impl<T> Drop for Callback<T> {
+    fn drop(&mut self) {
+        /* the callback was never sent, hence the closure cannot be called */
+        if let Some(port) = self.port.take() {
+            release(&port);
+        }
+    }
+}
@@ -1,8 +1,10 @@
-use std::{cell::RefCell, collections::HashMap, pin::Pin, rc::Rc, task::{Context, Poll}};
+use std::{cell::{Cell, RefCell, RefMut}, collections::{HashMap, VecDeque}, pin::Pin, rc::Rc, task::{self, Poll}, time::Duration};
 
 use futures_channel::oneshot;
 use futures_core::{future::LocalBoxFuture, Future};
-use futures_util::{future::{self, Shared}, FutureExt};
+use futures_util::{future::{self, Shared}, task::noop_waker_ref, FutureExt};
+
+use crate::{Metadata, Overflow};
 
 #[doc(hidden)]
 pub trait Client {
@@ -10,72 +12,813 @@ pub trait Client {
     type Response;
 }
 
+/* the client of builders that have not been configured with a client */
+impl Client for () {
+    type Request = ();
+    type Response = ();
+}
+
+/// The options of a client, these are set via the methods of [`crate::Builder`]
+pub(crate) struct Options<Request, Response> {
+    pub(crate) max_in_flight: Option<usize>,
+    pub(crate) overflow: Overflow,
+    pub(crate) interceptors: Vec<Rc<dyn Interceptor<Request, Response>>>,
+    /* tells the server of a remote object to shut down once the last clone of the client is dropped */
+    pub(crate) release: Option<Rc<crate::remote::Release>>,
+}
+
+impl<Request, Response> Default for Options<Request, Response> {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            overflow: Overflow::default(),
+            interceptors: Vec::new(),
+            release: None,
+        }
+    }
+}
+
+/// An interceptor wraps the requests that a client sends, e.g., for logging, caching, or
+/// retrying requests. Interceptors are added using [`crate::Builder::with_interceptor`] and
+/// receive the decoded request together with the [`Next`] interceptor, which they can run
+/// one or more times to send the request or skip to return a response or an error directly.
+/// Notifications are not intercepted unless they are acknowledged or abortable. Responses of
+/// methods whose return values are posted or remote cannot be returned without sending the
+/// request since they lack the posted values, such calls fail with [`crate::Error::Rejected`].
+/// ```rust
+/// struct Retry;
+/// impl Interceptor<ImageRequest, ImageResponse> for Retry {
+///     fn call<'a>(
+///         &'a self,
+///         _context: Context,
+///         request: ImageRequest,
+///         next: Next<'a, ImageRequest, ImageResponse>
+///     ) -> LocalBoxFuture<'a, Result<ImageResponse, web_rpc::Error>> {
+///         Box::pin(async move {
+///             match next.run(request.clone()).await {
+///                 Err(web_rpc::Error::Overloaded) => next.run(request).await,
+///                 response => response,
+///             }
+///         })
+///     }
+/// }
+/// ```
+pub trait Interceptor<Request, Response> {
+    fn call<'a>(
+        &'a self,
+        context: Context,
+        request: Request,
+        next: Next<'a, Request, Response>
+    ) -> LocalBoxFuture<'a, Result<Response, crate::Error>>;
+}
+
+/// Information about the call that an [`Interceptor`] is called for
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Context {
+    /// The name of the method that is called
+    pub method: &'static str,
+}
+
+/// Information about a request that was sent by [`Next::run`]
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Sent {
+    /// The sequence id of the request
+    pub seq_id: usize,
+    /// The size of the serialized request in bytes, this does not include the posted values
+    pub size: usize,
+}
+
+/// The rest of the interceptors and the client that an [`Interceptor`] wraps
+pub struct Next<'a, Request, Response> {
+    context: Context,
+    interceptors: &'a [Rc<dyn Interceptor<Request, Response>>],
+    send: &'a dyn Fn(Request) -> LocalBoxFuture<'a, Result<Response, crate::Error>>,
+    sent: &'a Cell<Option<Sent>>,
+    metadata: &'a RefCell<Metadata>,
+    response_metadata: &'a RefCell<Option<Metadata>>,
+}
+
+impl<Request, Response> Next<'_, Request, Response> {
+    /// Run the rest of the interceptors and send `request`. This method can be called more
+    /// than once to retry a request. Note that values that are transferred cannot be sent
+    /// more than once and that posted return values are only passed on to the caller if the
+    /// response returned by this method is the one that the interceptor returns.
+    pub async fn run(&self, request: Request) -> Result<Response, crate::Error> {
+        match self.interceptors.split_first() {
+            Some((interceptor, interceptors)) => {
+                let next = Next { interceptors, ..*self };
+                interceptor.call(self.context, request, next).await
+            },
+            None => (self.send)(request).await,
+        }
+    }
+
+    /// Get the information about the request that was sent last, this is `None` if no
+    /// request has been sent yet
+    pub fn sent(&self) -> Option<Sent> {
+        self.sent.get()
+    }
+
+    /// Access the metadata that is sent with the request, this includes the metadata of the
+    /// client and can be modified before calling [`Next::run`]. Note that the returned reference
+    /// must be dropped before calling [`Next::run`].
+    pub fn metadata(&self) -> RefMut<'_, Metadata> {
+        self.metadata.borrow_mut()
+    }
+
+    /// Get the metadata of the response that was received last, this is `None` if no response
+    /// has been received yet
+    pub fn response_metadata(&self) -> Option<Metadata> {
+        self.response_metadata.borrow().clone()
+    }
+}
+
+impl<Request, Response> Clone for Next<'_, Request, Response> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Request, Response> Copy for Next<'_, Request, Response> {}
+
+/// The statistics of a client, these are returned by the `stats` method of the generated
+/// clients. The byte counts only include the serialized part of the messages and not the
+/// posted Javascript values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of requests that have been sent and whose responses have not been received
+    pub in_flight: usize,
+    /// The total number of requests and notifications that have been sent per method
+    pub requests: HashMap<&'static str, u64>,
+    /// The total number of requests that were aborted by dropping their futures
+    pub aborts: u64,
+    /// The total number of requests that failed with a [`crate::Error`]
+    pub errors: u64,
+    /// The total number of bytes that have been sent
+    pub bytes_sent: u64,
+    /// The total number of bytes that have been received
+    pub bytes_received: u64,
+}
+
+#[doc(hidden)]
+pub type ResponseCallback<Response> = Box<dyn FnOnce(Result<(Response, Metadata, js_sys::Array), crate::Error>)>;
+
 #[doc(hidden)]
-pub type CallbackMap<Response> = HashMap<
-    usize,
-    oneshot::Sender<(Response, js_sys::Array)>
->;
+pub type CallbackMap<Response> = HashMap<usize, ResponseCallback<Response>>;
+
+/// Serializes a request from its sequence id, metadata, and timeout into an `ArrayBuffer`,
+/// returning the buffer together with the posted and transferred values of the request
+type RequestSerializer<Request> = Rc<dyn Fn(usize, Metadata, Option<Duration>, Request)
+    -> (js_sys::ArrayBuffer, js_sys::Array, js_sys::Array)>;
+
+/// Runs the interceptors and sends a request, resolving to the response and its posted values
+/// Sends a request, the posted values of the response are `None` if an interceptor returned the
+/// response without sending the request
+type Call<Request, Response> = Rc<dyn Fn(&'static str, CallOptions, Request, js_sys::Array, js_sys::Array)
+    -> LocalBoxFuture<'static, Result<(Response, Option<js_sys::Array>), crate::Error>>>;
+
+/// The options of a single call
+struct CallOptions {
+    metadata: Metadata,
+    timeout: Option<Duration>,
+    cancellation: Rc<Cancellation>,
+}
+
+/// Sends a notification
+type Notify<Request> = Rc<dyn Fn(&'static str, Metadata, Request, js_sys::Array, js_sys::Array)>;
 
 #[doc(hidden)]
-pub type Configuration<Request, Response> = (
-    Rc<RefCell<CallbackMap<Response>>>,
-    crate::port::Port,
-    Rc<gloo_events::EventListener>,
-    Shared<LocalBoxFuture<'static, ()>>,
-    Rc<dyn Fn(usize, Request) -> Vec<u8>>,
-    Rc<dyn Fn(usize)>,
-);
+pub struct Configuration<Request, Response> {
+    _listener: Rc<gloo_events::EventListener>,
+    dispatcher: Shared<LocalBoxFuture<'static, ()>>,
+    call: Call<Request, Response>,
+    notify: Notify<Request>,
+    cancel_sender: Rc<dyn Fn(usize)>,
+    permits: Option<Rc<Semaphore>>,
+    overflow: Overflow,
+    /* the metadata that is sent with every request and notification */
+    metadata: Rc<Metadata>,
+    /* how long each request that is sent waits for its response */
+    timeout: Option<Duration>,
+    /* set once the server on the other side of the channel has shut down */
+    shutdown: Rc<Cell<bool>>,
+    stats: Rc<RefCell<Stats>>,
+    _release: Option<Rc<crate::remote::Release>>,
+}
+
+impl<Request, Response> Clone for Configuration<Request, Response> {
+    fn clone(&self) -> Self {
+        Self {
+            _listener: self._listener.clone(),
+            dispatcher: self.dispatcher.clone(),
+            call: self.call.clone(),
+            notify: self.notify.clone(),
+            cancel_sender: self.cancel_sender.clone(),
+            permits: self.permits.clone(),
+            overflow: self.overflow,
+            metadata: self.metadata.clone(),
+            timeout: self.timeout,
+            shutdown: self.shutdown.clone(),
+            stats: self.stats.clone(),
+            _release: self._release.clone(),
+        }
+    }
+}
+
+impl<Request: 'static, Response: 'static> Configuration<Request, Response> {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        port: crate::interface::Sender,
+        listener: Rc<gloo_events::EventListener>,
+        dispatcher: Shared<LocalBoxFuture<'static, ()>>,
+        request_serializer: RequestSerializer<Request>,
+        register_callback: Rc<dyn Fn(usize, ResponseCallback<Response>)>,
+        abort_sender: Rc<dyn Fn(usize)>,
+        cancel_sender: Rc<dyn Fn(usize)>,
+        shutdown: Rc<Cell<bool>>,
+        stats: Rc<RefCell<Stats>>,
+        options: Options<Request, Response>,
+    ) -> Self {
+        let transport = Rc::new(Transport {
+            port,
+            seq_id: Default::default(),
+            request_serializer,
+            register_callback,
+            abort_sender,
+            shutdown: shutdown.clone(),
+            stats: stats.clone(),
+        });
+        /* the interceptor that was added last is called first */
+        let interceptors = options.interceptors.into_iter()
+            .rev()
+            .collect::<Rc<[_]>>();
+        let call: Call<Request, Response> = {
+            let transport = transport.clone();
+            Rc::new(move |method, CallOptions { metadata, timeout, cancellation }, request, post_args, transfer_args| {
+                let transport = transport.clone();
+                let interceptors = interceptors.clone();
+                async move {
+                    let sent: Cell<Option<Sent>> = Default::default();
+                    let metadata = RefCell::new(metadata);
+                    let response_metadata: RefCell<Option<Metadata>> = Default::default();
+                    let post_response: Cell<Option<js_sys::Array>> = Default::default();
+                    let send = |request| {
+                        /* copy the arguments so that the request can be sent more than once */
+                        let post_args = post_args.slice(0, post_args.length());
+                        let transfer_args = transfer_args.slice(0, transfer_args.length());
+                        let metadata = metadata.borrow().clone();
+                        let (transport, sent, cancellation, response_metadata, post_response) =
+                            (&transport, &sent, &cancellation, &response_metadata, &post_response);
+                        async move {
+                            let (response, metadata, post) = transport.send(
+                                method, metadata, timeout, request, post_args, transfer_args, sent, cancellation
+                            ).await?;
+                            response_metadata.replace(Some(metadata));
+                            post_response.set(Some(post));
+                            Ok(response)
+                        }.boxed_local()
+                    };
+                    let next = Next {
+                        context: Context { method },
+                        interceptors: &interceptors,
+                        send: &send,
+                        sent: &sent,
+                        metadata: &metadata,
+                        response_metadata: &response_metadata,
+                    };
+                    let response = next.run(request).await?;
+                    Ok((response, post_response.take()))
+                }.boxed_local()
+            })
+        };
+        let notify: Notify<Request> = Rc::new(move |method, metadata, request, post_args, transfer_args| {
+            let seq_id = transport.next_seq_id();
+            transport.post(method, seq_id, metadata, None, request, post_args, transfer_args);
+        });
+        Self {
+            _listener: listener,
+            dispatcher,
+            call,
+            notify,
+            cancel_sender,
+            permits: options.max_in_flight.map(|max_in_flight| Rc::new(Semaphore::new(max_in_flight))),
+            overflow: options.overflow,
+            metadata: Default::default(),
+            timeout: None,
+            shutdown,
+            stats,
+            _release: options.release,
+        }
+    }
+
+    /// Derive the configuration for the client of a supertrait. The requests of the
+    /// supertrait's client are wrapped using `into_request` and its responses are
+    /// extracted using `from_response`. The interceptors, the sequence ids, the permits,
+    /// the shutdown state, and the statistics are shared with this configuration and the
+    /// metadata and the timeout are copied from this configuration.
+    pub fn map<SubRequest: 'static, SubResponse: 'static>(
+        &self,
+        into_request: fn(SubRequest) -> Request,
+        from_response: fn(Response) -> Option<SubResponse>,
+    ) -> Configuration<SubRequest, SubResponse> {
+        let call = self.call.clone();
+        let notify = self.notify.clone();
+        Configuration {
+            _listener: self._listener.clone(),
+            dispatcher: self.dispatcher.clone(),
+            call: Rc::new(move |method, options, request, post_args, transfer_args| {
+                let response = call(method, options, into_request(request), post_args, transfer_args);
+                async move {
+                    response.await.map(|(response, post_response)| {
+                        let response = from_response(response)
+                            .expect("received incorrect response variant");
+                        (response, post_response)
+                    })
+                }.boxed_local()
+            }),
+            notify: Rc::new(move |method, metadata, request, post_args, transfer_args|
+                notify(method, metadata, into_request(request), post_args, transfer_args)),
+            cancel_sender: self.cancel_sender.clone(),
+            permits: self.permits.clone(),
+            overflow: self.overflow,
+            metadata: self.metadata.clone(),
+            timeout: self.timeout,
+            shutdown: self.shutdown.clone(),
+            stats: self.stats.clone(),
+            _release: self._release.clone(),
+        }
+    }
+
+    /// Get the current statistics of the client
+    pub fn stats(&self) -> Stats {
+        self.stats.borrow().clone()
+    }
+
+    /// Create a configuration that sends `key` and `value` as metadata in addition to the
+    /// metadata of this configuration, everything else is shared with this configuration
+    pub fn with_metadata(&self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        let mut metadata = (*self.metadata).clone();
+        metadata.insert(key.into(), value.into());
+        Self {
+            metadata: Rc::new(metadata),
+            ..self.clone()
+        }
+    }
+
+    /// Create a configuration whose requests fail with [`crate::Error::Timeout`] and are aborted
+    /// if their responses are not received within `timeout`, everything else is shared with this
+    /// configuration. The timeout is sent to the server, see [`crate::RequestContext::remaining`].
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Return a future that immediately resolves to `error`
+    fn fail<T: 'static>(&self, error: crate::Error) -> RequestFuture<T> {
+        self.stats.borrow_mut().errors += 1;
+        RequestFuture::new(future::ready(Err(error)), self.dispatcher.clone(), None)
+    }
+
+    /// Send a notification, i.e., a request without a response. Notifications are dropped
+    /// once the server has shut down.
+    pub fn notify(
+        &self,
+        method: &'static str,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array
+    ) {
+        if !self.shutdown.get() {
+            (self.notify)(method, (*self.metadata).clone(), request, post_args, transfer_args);
+        }
+    }
+
+    /// Send a notification and return a handle that aborts the notification when dropped.
+    /// Unlike other notifications, these notifications are sent as requests, i.e., they are
+    /// intercepted and limited by the number of requests in flight. The request is driven in
+    /// the background so that it is sent and releases its permit without polling the handle.
+    pub fn notify_abortable(
+        &self,
+        method: &'static str,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array
+    ) -> NotificationHandle {
+        let request = self.request(method, request, post_args, transfer_args, |_, _| ());
+        let cancellation = request.cancellation.clone();
+        let (request, abort_handle) = future::abortable(request.into_result());
+        wasm_bindgen_futures::spawn_local(request.map(|_| ()));
+        NotificationHandle { abort_handle: Some(abort_handle), cancellation }
+    }
+
+    /// Send a request and return a future that resolves to the response once it has been
+    /// unpacked using `unpack`. If the client has a limit on the number of requests in flight
+    /// and this limit has been reached, the request is either sent once a permit is available
+    /// or the future resolves to [`crate::Error::Busy`]. If the server has shut down, the
+    /// future resolves to [`crate::Error::Shutdown`].
+    pub fn request<T: 'static>(
+        &self,
+        method: &'static str,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array,
+        unpack: impl FnOnce(Response, js_sys::Array) -> T + 'static,
+    ) -> RequestFuture<T> {
+        self.send_request(method, request, post_args, transfer_args, false, unpack)
+    }
+
+    /// Send a request like [`Configuration::request`] for a method whose return value is posted.
+    /// Since the posted values are only received from the server, the future resolves to
+    /// [`crate::Error::Rejected`] if an interceptor returns a response without sending the request.
+    pub fn request_posted<T: 'static>(
+        &self,
+        method: &'static str,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array,
+        unpack: impl FnOnce(Response, js_sys::Array) -> T + 'static,
+    ) -> RequestFuture<T> {
+        self.send_request(method, request, post_args, transfer_args, true, unpack)
+    }
+
+    fn send_request<T: 'static>(
+        &self,
+        method: &'static str,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array,
+        posted: bool,
+        unpack: impl FnOnce(Response, js_sys::Array) -> T + 'static,
+    ) -> RequestFuture<T> {
+        if self.shutdown.get() {
+            return self.fail(crate::Error::Shutdown);
+        }
+        let permit = match &self.permits {
+            None => future::ready(None).left_future(),
+            Some(permits) => match permits.try_acquire() {
+                Some(permit) => future::ready(Some(permit)).left_future(),
+                None if self.overflow == Overflow::Reject =>
+                    return self.fail(crate::Error::Busy),
+                None => permits.clone().acquire().map(Some).right_future(),
+            }
+        };
+        let call = self.call.clone();
+        let cancellation = Rc::new(Cancellation {
+            cancelled: Default::default(),
+            seq_id: Default::default(),
+            cancel_sender: self.cancel_sender.clone(),
+        });
+        let options = CallOptions {
+            metadata: (*self.metadata).clone(),
+            timeout: self.timeout,
+            cancellation: cancellation.clone(),
+        };
+        let stats = self.stats.clone();
+        let mut result = async move {
+            let _permit = permit.await;
+            let result = call(method, options, request, post_args, transfer_args).await
+                .and_then(|(response, post_response)| match post_response {
+                    Some(post_response) => Ok((response, post_response)),
+                    None if posted => Err(crate::Error::Rejected(format!(
+                        "an interceptor returned a response to `{method}` without its posted values"
+                    ))),
+                    None => Ok((response, js_sys::Array::new())),
+                });
+            if result.is_err() {
+                stats.borrow_mut().errors += 1;
+            }
+            result.map(|(response, post_response)| unpack(response, post_response))
+        }.boxed_local();
+        /* poll the request once so that it is sent synchronously unless it needs to wait */
+        let mut context = task::Context::from_waker(noop_waker_ref());
+        if let Poll::Ready(result) = result.poll_unpin(&mut context) {
+            return RequestFuture::new(future::ready(result), self.dispatcher.clone(), None);
+        }
+        RequestFuture::new(result, self.dispatcher.clone(), Some(cancellation))
+    }
+}
+
+/// Sends the requests of a client and its supertraits' clients
+struct Transport<Request, Response> {
+    port: crate::interface::Sender,
+    seq_id: Cell<usize>,
+    request_serializer: RequestSerializer<Request>,
+    register_callback: Rc<dyn Fn(usize, ResponseCallback<Response>)>,
+    abort_sender: Rc<dyn Fn(usize)>,
+    shutdown: Rc<Cell<bool>>,
+    stats: Rc<RefCell<Stats>>,
+}
+
+impl<Request, Response: 'static> Transport<Request, Response> {
+    fn next_seq_id(&self) -> usize {
+        let seq_id = self.seq_id.get();
+        self.seq_id.set(seq_id.wrapping_add(1));
+        seq_id
+    }
+
+    /// Serialize a request and post it together with the posted and transferred arguments,
+    /// returning the number of serialized bytes
+    #[allow(clippy::too_many_arguments)]
+    fn post(
+        &self,
+        method: &'static str,
+        seq_id: usize,
+        metadata: Metadata,
+        timeout: Option<Duration>,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array
+    ) -> usize {
+        /* the values posted by the serialized request precede the posted arguments */
+        let (buffer, posted, transferred) = (self.request_serializer)(seq_id, metadata, timeout, request);
+        let (post_args, transfer_args) = (posted.concat(&post_args), transferred.concat(&transfer_args));
+        post_args.unshift(&buffer);
+        transfer_args.unshift(&buffer);
+        self.port.post_message(&post_args, &transfer_args).unwrap();
+        let len = buffer.byte_length() as usize;
+        let mut stats = self.stats.borrow_mut();
+        *stats.requests.entry(method).or_default() += 1;
+        stats.bytes_sent += len as u64;
+        len
+    }
+
+    /// Send a request and wait for its response, the request is aborted if the returned
+    /// future is dropped or the timeout elapses before the response has been received
+    #[allow(clippy::too_many_arguments)]
+    async fn send(
+        &self,
+        method: &'static str,
+        metadata: Metadata,
+        timeout: Option<Duration>,
+        request: Request,
+        post_args: js_sys::Array,
+        transfer_args: js_sys::Array,
+        sent: &Cell<Option<Sent>>,
+        cancellation: &Cancellation,
+    ) -> Result<(Response, Metadata, js_sys::Array), crate::Error> {
+        /* the server may have shut down while waiting for a permit or between retries */
+        if self.shutdown.get() {
+            return Err(crate::Error::Shutdown);
+        }
+        if cancellation.cancelled.get() {
+            return Err(crate::Error::Cancelled);
+        }
+        let seq_id = self.next_seq_id();
+        let (response_tx, response_rx) = oneshot::channel();
+        let pending = Rc::new(Cell::new(true));
+        let callback = {
+            let pending = pending.clone();
+            let stats = self.stats.clone();
+            move |response| {
+                pending.set(false);
+                stats.borrow_mut().in_flight -= 1;
+                let _ = response_tx.send(response);
+            }
+        };
+        (self.register_callback)(seq_id, Box::new(callback));
+        let size = self.post(method, seq_id, metadata, timeout, request, post_args, transfer_args);
+        self.stats.borrow_mut().in_flight += 1;
+        sent.set(Some(Sent { seq_id, size }));
+        cancellation.seq_id.set(Some(seq_id));
+        let _abort = RequestAbort {
+            seq_id,
+            pending,
+            abort_sender: self.abort_sender.clone(),
+            stats: self.stats.clone(),
+        };
+        let response = match timeout {
+            None => response_rx.await.unwrap(),
+            Some(timeout) => {
+                let timeout = gloo_timers::future::sleep(timeout);
+                match future::select(response_rx, timeout).await {
+                    future::Either::Left((response, _)) => response.unwrap(),
+                    future::Either::Right(_) => Err(crate::Error::Timeout),
+                }
+            }
+        };
+        cancellation.seq_id.set(None);
+        response
+    }
+}
+
+/// Aborts a request that is still pending when dropped
+struct RequestAbort {
+    seq_id: usize,
+    pending: Rc<Cell<bool>>,
+    abort_sender: Rc<dyn Fn(usize)>,
+    stats: Rc<RefCell<Stats>>,
+}
+
+impl Drop for RequestAbort {
+    fn drop(&mut self) {
+        if self.pending.get() {
+            let mut stats = self.stats.borrow_mut();
+            stats.in_flight -= 1;
+            stats.aborts += 1;
+            drop(stats);
+            (self.abort_sender)(self.seq_id);
+        }
+    }
+}
+
+/// Cancels the request of a call, see [`RequestFuture::cancel`]
+struct Cancellation {
+    cancelled: Cell<bool>,
+    /* the sequence id of the request that is in flight */
+    seq_id: Cell<Option<usize>>,
+    cancel_sender: Rc<dyn Fn(usize)>,
+}
+
+impl Cancellation {
+    fn cancel(&self) {
+        if !self.cancelled.replace(true) {
+            if let Some(seq_id) = self.seq_id.get() {
+                (self.cancel_sender)(seq_id);
+            }
+        }
+    }
+}
+
+/// A semaphore for limiting the number of requests in flight
+struct Semaphore {
+    available: Cell<usize>,
+    waiters: RefCell<VecDeque<oneshot::Sender<Permit>>>,
+}
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            available: Cell::new(permits),
+            waiters: Default::default(),
+        }
+    }
+
+    fn try_acquire(self: &Rc<Self>) -> Option<Permit> {
+        let available = self.available.get();
+        (available > 0 && self.waiters.borrow().is_empty()).then(|| {
+            self.available.set(available - 1);
+            Permit { semaphore: self.clone() }
+        })
+    }
+
+    async fn acquire(self: Rc<Self>) -> Permit {
+        match self.try_acquire() {
+            Some(permit) => permit,
+            None => {
+                let (permit_tx, permit_rx) = oneshot::channel();
+                self.waiters.borrow_mut().push_back(permit_tx);
+                permit_rx.await.expect("semaphore was dropped")
+            }
+        }
+    }
+}
+
+/// A permit to have a request in flight. Dropping the permit passes it on to the next
+/// waiting request or returns it to the semaphore.
+struct Permit {
+    semaphore: Rc<Semaphore>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut permit = Permit { semaphore: self.semaphore.clone() };
+        loop {
+            let waiter = self.semaphore.waiters.borrow_mut().pop_front();
+            match waiter {
+                Some(waiter) => match waiter.send(permit) {
+                    Ok(_) => break,
+                    /* the waiting request was dropped, try the next one */
+                    Err(returned) => permit = returned,
+                },
+                None => {
+                    let semaphore = permit.semaphore.clone();
+                    std::mem::forget(permit);
+                    semaphore.available.set(semaphore.available.get() + 1);
+                    break;
+                }
+            }
+        }
+    }
+}
 
 /// This future represents a RPC request that is currently being executed. Note that
-/// dropping this future will result in the RPC request being cancelled
+/// dropping this future will result in the RPC request being aborted. Awaiting this
+/// future panics if the request fails, use [`RequestFuture::into_result`] to handle
+/// the [`crate::Error`] instead.
 #[must_use = "Either await this future or remove the return type from the RPC method"]
 pub struct RequestFuture<T: 'static> {
-    result: LocalBoxFuture<'static, T>,
-    abort: Pin<Box<RequestAbort>>,
+    result: LocalBoxFuture<'static, Result<T, crate::Error>>,
+    cancellation: Option<Rc<Cancellation>>,
 }
 
 impl<T> RequestFuture<T> {
-    pub fn new(
-        result: impl Future<Output = T> + 'static,
+    fn new(
+        result: impl Future<Output = Result<T, crate::Error>> + 'static,
         dispatcher: Shared<LocalBoxFuture<'static, ()>>,
-        abort: Box<dyn Fn()>,
+        cancellation: Option<Rc<Cancellation>>,
     ) -> Self {
         Self {
+            cancellation,
             result: future::select(result.boxed_local(), dispatcher)
-                .map(|select| match select {
-                    future::Either::Left((result, _)) => result,
-                    future::Either::Right(_) => unreachable!("dispatcher should not complete"),
+                .then(|select| match select {
+                    future::Either::Left((result, _)) => future::ready(result).left_future(),
+                    /* the dispatcher completes once the other side has closed its virtual channel,
+                       in which case the pending requests have failed */
+                    future::Either::Right((_, result)) => result.right_future(),
                 })
                 .boxed_local(),
-            abort: Box::pin(RequestAbort {
-                active: true,
-                abort
-            })
         }
     }
 }
 
-struct RequestAbort {
-    active: bool,
-    abort: Box<dyn Fn()>,
-}
+impl<T> RequestFuture<T> {
+    /// Convert this future into a future that resolves to an error if the request fails
+    pub fn into_result(self) -> TryRequestFuture<T> {
+        TryRequestFuture { inner: self }
+    }
 
-impl Drop for RequestAbort {
-    fn drop(&mut self) {
-        if self.active {
-            (self.abort)();
+    /// Cancel the request without dropping this future. Unlike dropping the future, the server
+    /// replies to a cancelled request: methods of services generated with the `cooperative`
+    /// argument can return a partial result while other methods are dropped, in which case the
+    /// request fails with [`crate::Error::Cancelled`]. This has no effect on completed requests.
+    pub fn cancel(&self) {
+        if let Some(cancellation) = &self.cancellation {
+            cancellation.cancel();
         }
     }
+
+    fn poll_result(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<T, crate::Error>> {
+        self.result.poll_unpin(cx)
+    }
 }
 
 impl<T> Future for RequestFuture<T> {
     type Output = T;
 
-    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let poll_result = self.as_mut().result.poll_unpin(cx);
-        if matches!(poll_result, Poll::Ready(_)) {
-            self.as_mut().abort.active = false;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.poll_result(cx).map(|result| match result {
+            Ok(response) => response,
+            Err(error) => panic!("RPC request failed: {error}"),
+        })
+    }
+}
+
+/// This future is returned from [`RequestFuture::into_result`] and resolves to an error
+/// if the request fails. As with [`RequestFuture`], dropping this future will result in
+/// the RPC request being aborted.
+#[must_use = "Either await this future or remove the return type from the RPC method"]
+pub struct TryRequestFuture<T: 'static> {
+    inner: RequestFuture<T>,
+}
+
+impl<T> TryRequestFuture<T> {
+    /// Cancel the request without dropping this future, see [`RequestFuture::cancel`]
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+}
+
+impl<T> Future for TryRequestFuture<T> {
+    type Output = Result<T, crate::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        self.inner.poll_result(cx)
+    }
+}
+
+/// A handle to a notification of a method that was annotated with `#[abortable]`. Dropping
+/// the handle aborts the notification if the server is still executing it, in which case the
+/// server drops the future of the method. Use [`NotificationHandle::detach`] to let the
+/// notification run to completion instead.
+#[must_use = "Dropping the handle aborts the notification, use `detach` to let it complete"]
+pub struct NotificationHandle {
+    /* aborts the task that drives the request, which drops the request and hence aborts it */
+    abort_handle: Option<future::AbortHandle>,
+    cancellation: Option<Rc<Cancellation>>,
+}
+
+impl NotificationHandle {
+    /// Cancel the notification without aborting it, see [`RequestFuture::cancel`]
+    pub fn cancel(&self) {
+        if let Some(cancellation) = &self.cancellation {
+            cancellation.cancel();
+        }
+    }
+
+    /// Let the notification run to completion without waiting for it
+    pub fn detach(mut self) {
+        self.abort_handle = None;
+    }
+}
+
+impl Drop for NotificationHandle {
+    fn drop(&mut self) {
+        if let Some(abort_handle) = self.abort_handle.take() {
+            abort_handle.abort();
         }
-        poll_result
     }
 }
@@ -0,0 +1,108 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use js_sys::{Array, ArrayBuffer, Uint8Array};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::post;
+
+/// The maximum size of the chunks in which messages are copied between Rust and Javascript memory
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[cfg(feature = "copy-stats")]
+thread_local! {
+    static COPIED: std::cell::Cell<Copied> = Default::default();
+}
+
+/// The bytes that the codec copied between Rust and Javascript memory on this thread
+#[cfg(feature = "copy-stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Copied {
+    /// The total number of copied bytes
+    pub bytes: u64,
+    /// The size of the largest single copy
+    pub largest: u32,
+}
+
+/// Get the bytes that the codec copied between Rust and Javascript memory on this thread
+#[cfg(feature = "copy-stats")]
+pub fn copied() -> Copied {
+    COPIED.with(std::cell::Cell::get)
+}
+
+#[cfg(feature = "copy-stats")]
+fn record(len: u32) {
+    COPIED.with(|copied| {
+        let Copied { bytes, largest } = copied.get();
+        copied.set(Copied { bytes: bytes + len as u64, largest: largest.max(len) });
+    });
+}
+
+#[cfg(not(feature = "copy-stats"))]
+fn record(_len: u32) {}
+
+/// Serialize `value` directly into a new `ArrayBuffer` of the exact size of the message, so that
+/// the serialized message is never held in Rust memory as a whole. Returns the buffer together
+/// with the values that were posted and transferred by the serialized types.
+pub(crate) fn encode<T: Serialize>(value: &T) -> (ArrayBuffer, Array, Array) {
+    /* the size is measured by walking the value without writing or allocating anything, which is
+       cheaper than serializing into a Rust buffer that can hold the whole message and copying it,
+       and keeps the memory that a large message needs on the Rust side to a single chunk */
+    let size = post::measure(|| bincode::serialized_size(value).unwrap()) as u32;
+    let array = Uint8Array::new_with_length(size);
+    let ((), posted, transferred) = post::collect(|| {
+        let mut writer = BufWriter::with_capacity(chunk_size(size), JsWriter { array: &array, offset: 0 });
+        bincode::serialize_into(&mut writer, value).unwrap();
+        writer.flush().unwrap();
+    });
+    (array.buffer(), posted, transferred)
+}
+
+/// Deserialize a message directly from `buffer`, the serialized types take their posted values
+/// from the front of `post`
+pub(crate) fn decode<T: DeserializeOwned>(buffer: &ArrayBuffer, post: &Array) -> bincode::Result<T> {
+    let array = Uint8Array::new(buffer);
+    post::attach(post, || {
+        let reader = BufReader::with_capacity(chunk_size(array.length()), JsReader { array: &array, offset: 0 });
+        bincode::deserialize_from(reader)
+    })
+}
+
+fn chunk_size(size: u32) -> usize {
+    CHUNK_SIZE.min(size as usize).max(1)
+}
+
+/// Copies the written bytes into a `Uint8Array`
+struct JsWriter<'a> {
+    array: &'a Uint8Array,
+    offset: u32,
+}
+
+impl Write for JsWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let len = (bytes.len() as u32).min(self.array.length() - self.offset);
+        self.array.subarray(self.offset, self.offset + len).copy_from(&bytes[..len as usize]);
+        self.offset += len;
+        record(len);
+        Ok(len as usize)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Copies the read bytes out of a `Uint8Array`
+struct JsReader<'a> {
+    array: &'a Uint8Array,
+    offset: u32,
+}
+
+impl Read for JsReader<'_> {
+    fn read(&mut self, bytes: &mut [u8]) -> std::io::Result<usize> {
+        let len = (bytes.len() as u32).min(self.array.length() - self.offset);
+        self.array.subarray(self.offset, self.offset + len).copy_to(&mut bytes[..len as usize]);
+        self.offset += len;
+        record(len);
+        Ok(len as usize)
+    }
+}
@@ -0,0 +1,81 @@
+/* without any algorithms, the arguments of the methods below are never used */
+#![cfg_attr(not(any(feature = "lz4", feature = "deflate")), allow(unused_variables))]
+
+/// The algorithms that can be used to compress the messages of an interface, see
+/// [`crate::Interface::with_compression`]. Each algorithm is only available if the cargo feature
+/// of the same name is enabled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// LZ4, which is fast but achieves a moderate compression ratio
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Deflate, which is slower than LZ4 but achieves a higher compression ratio
+    #[cfg(feature = "deflate")]
+    Deflate,
+}
+
+impl Compression {
+    /// The algorithms that this build can decompress, all of them are tried by [`Compression::parse`]
+    const ALL: &'static [Compression] = &[
+        #[cfg(feature = "lz4")]
+        Compression::Lz4,
+        #[cfg(feature = "deflate")]
+        Compression::Deflate,
+    ];
+
+    /// The name that precedes the messages that were compressed with this algorithm
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => "lz4",
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => "deflate",
+        }
+    }
+
+    /// The flag of this algorithm in the handshake, which contains the algorithms that a side of
+    /// the port can decompress
+    pub(crate) fn flag(self) -> u32 {
+        match self {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => 1,
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => 2,
+        }
+    }
+
+    /// The flags of all algorithms that this build can decompress
+    pub(crate) fn supported() -> u32 {
+        Self::ALL.iter().fold(0, |flags, compression| flags | compression.flag())
+    }
+
+    /// Find the algorithm with `name`
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|compression| compression.name() == name)
+    }
+
+    pub(crate) fn compress(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => lz4_flex::compress_prepend_size(bytes),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => miniz_oxide::deflate::compress_to_vec(bytes, 6),
+        }
+    }
+
+    /// Decompress `bytes`, failing if the decompressed bytes would exceed `max_size`
+    pub(crate) fn decompress(self, bytes: &[u8], max_size: usize) -> Option<Vec<u8>> {
+        match self {
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                let (size, _) = lz4_flex::block::uncompressed_size(bytes).ok()?;
+                match size <= max_size {
+                    true => lz4_flex::decompress_size_prepended(bytes).ok(),
+                    false => None,
+                }
+            },
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(bytes, max_size).ok(),
+        }
+    }
+}
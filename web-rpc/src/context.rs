@@ -0,0 +1,164 @@
+use std::{cell::{Cell, RefCell}, collections::HashMap, future::Future, pin::Pin, rc::Rc, task::{Context as TaskContext, Poll, Waker}, time::Duration};
+
+/// Metadata that is sent alongside requests and responses, e.g., authentication tokens,
+/// trace ids, or locales
+pub type Metadata = HashMap<String, String>;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<RequestContext>>> = const { RefCell::new(None) };
+}
+
+/// `RequestContext` provides information about the request that a server is executing. It can be
+/// accessed from within the methods of a service and its layers using [`RequestContext::current`]
+/// or, for services generated with the `context` argument of the [`macro@crate::service`] macro, via
+/// the `ctx` parameter of the methods.
+#[derive(Debug)]
+pub struct RequestContext {
+    seq_id: usize,
+    metadata: Metadata,
+    /* the time at which the client stops waiting for the response in milliseconds */
+    deadline: Option<f64>,
+    token: CancellationToken,
+    response_metadata: RefCell<Metadata>,
+}
+
+impl RequestContext {
+    pub(crate) fn new(seq_id: usize, metadata: Metadata, timeout: Option<Duration>) -> Self {
+        Self {
+            seq_id,
+            metadata,
+            deadline: timeout.map(|timeout| js_sys::Date::now() + timeout.as_secs_f64() * 1000.0),
+            token: Default::default(),
+            response_metadata: Default::default(),
+        }
+    }
+
+    /// Get the context of the request that is currently being executed. This returns `None`
+    /// if this method is not called while a server is executing a request.
+    pub fn current() -> Option<Rc<RequestContext>> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// The sequence id of the request, this is unique among the requests from a client
+    pub fn seq_id(&self) -> usize {
+        self.seq_id
+    }
+
+    /// The metadata that the client sent with the request
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// The time that remains until the client stops waiting for the response, this is `None`
+    /// if the client did not set a timeout, see the `with_timeout` method of the generated clients
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| {
+            Duration::from_secs_f64((deadline - js_sys::Date::now()).max(0.0) / 1000.0)
+        })
+    }
+
+    /// Check whether the client has aborted or cancelled the request or the server has aborted
+    /// it while shutting down
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Wait until the request has been cancelled, see [`RequestContext::is_cancelled`]
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Get the cancellation token of the request, which can be moved into other tasks
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Add metadata to the response, this metadata can be read by the client's interceptors
+    pub fn set_response_metadata(&self, key: impl Into<String>, value: impl Into<String>) {
+        self.response_metadata.borrow_mut().insert(key.into(), value.into());
+    }
+
+    pub(crate) fn take_response_metadata(&self) -> Metadata {
+        self.response_metadata.take()
+    }
+}
+
+/// A token that tells a method whether its request has been cancelled. Async methods of services
+/// that are generated with the `cooperative` argument of the [`macro@crate::service`] macro receive
+/// this token and are not dropped when their requests are cancelled. Instead, they can clean up and
+/// return a partial result, which is sent to the client.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Rc<TokenState>,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    /// Check whether the request has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.get()
+    }
+
+    /// Wait until the request has been cancelled
+    pub async fn cancelled(&self) {
+        std::future::poll_fn(|cx| match self.inner.cancelled.get() {
+            true => Poll::Ready(()),
+            false => {
+                let mut wakers = self.inner.wakers.borrow_mut();
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }).await
+    }
+
+    fn cancel(&self) {
+        self.inner.cancelled.set(true);
+        for waker in self.inner.wakers.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Make `context` the current context while `future` is being polled
+pub(crate) fn scope<F: Future>(context: Rc<RequestContext>, future: F) -> Scoped<F> {
+    Scoped {
+        context,
+        future: Box::pin(future),
+    }
+}
+
+pub(crate) struct Scoped<F> {
+    context: Rc<RequestContext>,
+    future: Pin<Box<F>>,
+}
+
+/// Restores the previous context when dropped, even if polling the future panicked
+struct Restore(Option<Rc<RequestContext>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let previous = CURRENT.with(|current| current.replace(Some(self.context.clone())));
+        let _restore = Restore(previous);
+        self.future.as_mut().poll(cx)
+    }
+}
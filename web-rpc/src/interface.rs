@@ -1,13 +1,20 @@
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::Rc};
+
 use futures_channel::{mpsc, oneshot};
-use futures_util::future;
+use futures_util::{future, StreamExt};
 use wasm_bindgen::{JsCast, JsValue};
 
+pub use crate::compression::Compression;
+
 /// An interface represents a [`crate::port::Port`] that has been fully initialised and
-/// has verified that the other end of the channel is ready to receive messages.
+/// has verified that the other end of the channel is ready to receive messages. An interface
+/// can also be a virtual channel that is multiplexed over the port of another interface, see
+/// [`Interface::open_channel`].
 pub struct Interface {
-    pub(crate) port: crate::port::Port,
-    pub(crate) listener: gloo_events::EventListener,
+    pub(crate) port: Sender,
+    pub(crate) listener: Rc<gloo_events::EventListener>,
     pub(crate) messages_rx: mpsc::UnboundedReceiver<js_sys::Array>,
+    channels: Rc<Channels>,
 }
 
 impl Interface {
@@ -17,29 +24,16 @@ impl Interface {
     /// the channel is ready.
     pub async fn new(port: impl Into<crate::port::Port>) -> Self {
         let port = port.into();
-        let (dispatcher_tx, dispatcher_rx) = mpsc::unbounded();
         let (ready_tx, ready_rx) = oneshot::channel();
-        let mut ready_tx = Option::from(ready_tx);
-        let listener = gloo_events::EventListener::new(port.event_target(), "message", move |event| {
-            let message = event.unchecked_ref::<web_sys::MessageEvent>().data();
-            match message.dyn_into::<js_sys::Array>() {
-                /* default path, enqueue the message for deserialization by the dispatcher */
-                Ok(array) => {
-                    let _ = dispatcher_tx.unbounded_send(array);
-                },
-                /* handshake path */
-                Err(_) => if let Some(ready_tx) = ready_tx.take() {
-                    let _ = ready_tx.send(());
-                }
-            }
-        });
-        /* needed for MessagePort */
-        port.start();
-        /* poll other end of the channel */
+        let interface = Self::listen(port.clone(), Some(ready_tx));
+        /* poll other end of the channel, the handshake contains the compressions that this
+           side can decompress */
+        let handshake = JsValue::from(Compression::supported());
         let port_cloned = port.clone();
+        let handshake_cloned = handshake.clone();
         let poll = async move {
             loop {
-                port_cloned.post_message(&JsValue::NULL, &JsValue::UNDEFINED).unwrap();
+                port_cloned.post_message(&handshake_cloned, &JsValue::UNDEFINED).unwrap();
                 gloo_timers::future::TimeoutFuture::new(10).await;
             }
         };
@@ -47,13 +41,476 @@ impl Interface {
         future::select(ready_rx, poll).await;
         /* at this point we know the other end's listener is available, but we may
            need to send one last message to indicate that we are available */
-        port.post_message(&JsValue::NULL, &JsValue::UNDEFINED).unwrap();
+        port.post_message(&handshake, &JsValue::UNDEFINED).unwrap();
         /* return the interface */
+        interface
+    }
+
+    /// Create an interface from a port of a [`web_sys::MessageChannel`] without waiting for the
+    /// other end. This is only possible if the other end is also used by an interface since the
+    /// messages that are posted to a port are queued until the port is started.
+    pub(crate) fn entangled(port: web_sys::MessagePort) -> Self {
+        Self::listen(port.into(), None)
+    }
+
+    /// Coalesce the messages that this interface sends within the same turn of the event loop into
+    /// a single posted message, see [`Batching`]. This applies to the requests of clients and to the
+    /// responses of servers that are built from this interface, but not to the virtual channels that
+    /// are opened or accepted from it. The other side of the port unbatches the messages regardless
+    /// of whether its interface uses batching.
+    pub fn with_batching(mut self, batching: Batching) -> Self {
+        self.port.batch = Some(Rc::new(Batch {
+            batching,
+            port: self.port.port.clone(),
+            pending: Default::default(),
+        }));
+        self
+    }
+
+    /// Split the encoded messages that this interface sends into chunks of at most `chunk_size`
+    /// bytes, which are posted as separate messages and reassembled on the other side of the port.
+    /// This is useful for transports that limit the size of messages and avoids stalling the event
+    /// loop of the receiver with a single huge message. Chunked messages are not batched, see
+    /// [`Interface::with_batching`]. Like [`Interface::with_batching`], this does not apply to the
+    /// virtual channels that are opened or accepted from this interface.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "the chunk size must not be zero");
+        self.port.chunk_size = Some(chunk_size.min(u32::MAX as usize) as u32);
+        self
+    }
+
+    /// Discard the messages received on the port of this interface whose size exceeds
+    /// `max_message_size` bytes, which applies to chunked messages once they have been reassembled,
+    /// see [`Interface::with_chunk_size`]. This protects the receiver from allocating arbitrarily large
+    /// messages and applies to all virtual channels of the port. The requests of discarded messages
+    /// are never executed and their responses are never received, so clients should use timeouts.
+    pub fn with_max_message_size(self, max_message_size: usize) -> Self {
+        self.channels.max_message_size.set(Some(max_message_size));
+        self
+    }
+
+    /// Compress the encoded messages that this interface sends with `compression` if they are
+    /// larger than `threshold` bytes. Messages are only compressed if the other side of the port
+    /// announced during the handshake that it can decompress them, i.e., if it was built with the
+    /// same cargo feature, and if compressing them reduces their size. Like
+    /// [`Interface::with_batching`], this does not apply to the virtual channels that are opened or
+    /// accepted from this interface.
+    pub fn with_compression(mut self, compression: Compression, threshold: usize) -> Self {
+        self.port.compressor = Some(Compressor {
+            compression,
+            threshold,
+            channels: self.channels.clone(),
+        });
+        self
+    }
+
+    /// Open a virtual channel over the port of this interface. The returned interface can be
+    /// used with [`crate::Builder`] like any other interface, while the other side of the port
+    /// receives the channel from [`Interface::accept_channel`]. Opening a channel is synchronous
+    /// and does not require a new port or a handshake since the other side is known to be ready.
+    /// Dropping the returned interface, or the client and the server built from it, closes the
+    /// channel on both sides.
+    pub fn open_channel(&self) -> Interface {
+        let id = self.channels.next_id.get();
+        /* the tags of the messages hold the ids shifted left by one */
+        assert!(id < 1 << 31, "the ids of the virtual channels have been exhausted");
+        self.channels.next_id.set(id + 1);
+        let (messages_tx, messages_rx) = mpsc::unbounded();
+        self.channels.routes.borrow_mut().insert((id, true), messages_tx);
+        /* tell the other side about the channel, this message only consists of the tag */
+        let tag = id << 1;
+        let _ = self.channels.port.post_message(&js_sys::Array::of1(&tag.into()), &JsValue::UNDEFINED);
+        self.channel(tag, messages_rx)
+    }
+
+    /// Wait for the other side of the port of this interface to open a virtual channel, see
+    /// [`Interface::open_channel`]. The channels are accepted in the order in which they were
+    /// opened, regardless of which interface of the port they were opened from.
+    pub async fn accept_channel(&self) -> Interface {
+        let (id, messages_rx) = std::future::poll_fn(|cx| self.channels.accepted_rx
+            .borrow_mut()
+            .poll_next_unpin(cx)).await
+            .expect("the channels keep the sender of the accepted channels");
+        self.channel((id << 1) | 1, messages_rx)
+    }
+
+    fn channel(&self, tag: u32, messages_rx: mpsc::UnboundedReceiver<js_sys::Array>) -> Interface {
+        Interface {
+            port: Sender {
+                port: self.channels.port.clone(),
+                tag: Some(tag),
+                chunk_size: None,
+                compressor: None,
+                batch: None,
+                _close: Some(Rc::new(Close { tag, channels: self.channels.clone() })),
+            },
+            listener: self.listener.clone(),
+            messages_rx,
+            channels: self.channels.clone(),
+        }
+    }
+
+    /// Attach a listener to `port` that forwards the messages to the dispatcher and notifies
+    /// `ready_tx` once the other end has responded to the handshake
+    fn listen(port: crate::port::Port, mut ready_tx: Option<oneshot::Sender<()>>) -> Self {
+        let (dispatcher_tx, dispatcher_rx) = mpsc::unbounded();
+        let (accepted_tx, accepted_rx) = mpsc::unbounded();
+        let channels = Rc::new(Channels {
+            port: port.clone(),
+            next_id: Default::default(),
+            routes: Default::default(),
+            chunks: Default::default(),
+            max_message_size: Default::default(),
+            peer_compressions: Default::default(),
+            accepted_tx,
+            accepted_rx: RefCell::new(accepted_rx),
+        });
+        let channels_cloned = channels.clone();
+        let listener = gloo_events::EventListener::new(port.event_target(), "message", move |event| {
+            let message = event.unchecked_ref::<web_sys::MessageEvent>().data();
+            match message.dyn_into::<js_sys::Array>() {
+                Ok(array) => Self::receive(array, &channels_cloned, &dispatcher_tx),
+                /* handshake path */
+                Err(handshake) => {
+                    let compressions = handshake.as_f64().unwrap_or_default() as u32;
+                    channels_cloned.peer_compressions.set(compressions);
+                    if let Some(ready_tx) = ready_tx.take() {
+                        let _ = ready_tx.send(());
+                    }
+                }
+            }
+        });
+        /* needed for MessagePort */
+        port.start();
         Self {
             messages_rx: dispatcher_rx,
-            listener,
-            port,
+            listener: Rc::new(listener),
+            port: Sender {
+                port,
+                tag: None,
+                chunk_size: None,
+                compressor: None,
+                batch: None,
+                _close: None,
+            },
+            channels,
+        }
+    }
+
+    /// Forward a received message to its virtual channel or to the dispatcher
+    fn receive(
+        array: js_sys::Array,
+        channels: &Channels,
+        dispatcher_tx: &mpsc::UnboundedSender<js_sys::Array>
+    ) {
+        /* batches consist of the messages that were sent within the same turn of the event loop */
+        if js_sys::Array::is_array(&array.get(0)) {
+            for message in array.iter() {
+                Self::receive(message.unchecked_into(), channels, dispatcher_tx);
+            }
+            return;
+        }
+        /* the release messages of remote objects are handled by the listeners of their servers */
+        if crate::remote::is_release(&array) {
+            return;
+        }
+        /* the messages of virtual channels start with the tag of their channel */
+        let tag = array.get(0).as_f64().map(|tag| {
+            array.shift();
+            tag as u32
+        });
+        /* a channel is closed with a message that only consists of its tag and null */
+        if let (Some(tag), true) = (tag, array.length() == 1 && array.get(0).is_null()) {
+            channels.close(tag);
+            return;
+        }
+        let Some(array) = channels.reassemble(tag, array).and_then(|array| channels.decompress(array)) else {
+            return;
+        };
+        match tag {
+            Some(tag) => channels.route(tag, array),
+            /* default path, enqueue the message for deserialization by the dispatcher */
+            None => {
+                let _ = dispatcher_tx.unbounded_send(array);
+            }
         }
     }
 }
 
+/// Batching decides when the messages that an interface sends within the same turn of the event
+/// loop are posted, see [`Interface::with_batching`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Batching {
+    /// The messages are posted in a microtask, i.e., once the currently running task yields to
+    /// the event loop
+    Microtask,
+    /// The messages are posted before the next repaint using `requestAnimationFrame`. This falls
+    /// back to [`Batching::Microtask`] if `requestAnimationFrame` is not available.
+    AnimationFrame,
+}
+
+/// The index of the encoded message in a posted message, compressed messages start with the name
+/// of their compression
+fn buffer_index(message: &js_sys::Array) -> u32 {
+    message.get(0).is_string() as u32
+}
+
+/// Compresses the encoded messages of an interface
+#[derive(Clone)]
+struct Compressor {
+    compression: Compression,
+    threshold: usize,
+    channels: Rc<Channels>,
+}
+
+impl Compressor {
+    /// Compress the encoded message if it exceeds the threshold, the other side can decompress
+    /// it, and compressing it reduces its size
+    fn compress(&self, message: &js_sys::Array, transfer: &js_sys::Array) {
+        let Ok(buffer) = message.get(0).dyn_into::<js_sys::ArrayBuffer>() else {
+            return;
+        };
+        let supported = self.channels.peer_compressions.get() & self.compression.flag() != 0;
+        if !supported || (buffer.byte_length() as usize) <= self.threshold {
+            return;
+        }
+        let compressed = self.compression.compress(&js_sys::Uint8Array::new(&buffer).to_vec());
+        if compressed.len() < buffer.byte_length() as usize {
+            let compressed = js_sys::Uint8Array::from(&compressed[..]).buffer();
+            message.set(0, compressed.clone().into());
+            message.unshift(&self.compression.name().into());
+            transfer.push(&compressed);
+        }
+    }
+}
+
+/// The chunks of a message that have been received so far
+#[derive(Default)]
+struct Chunks {
+    chunks: Vec<js_sys::Uint8Array>,
+    size: usize,
+}
+
+/// The messages of an interface that are waiting to be posted together
+struct Batch {
+    batching: Batching,
+    port: crate::port::Port,
+    /* the posted and transferred values of the pending messages */
+    pending: RefCell<Option<(js_sys::Array, js_sys::Array)>>,
+}
+
+impl Batch {
+    fn push(self: &Rc<Self>, message: &js_sys::Array, transfer: &js_sys::Array) {
+        let mut pending = self.pending.borrow_mut();
+        let (messages, transferred) = pending.get_or_insert_with(|| {
+            self.schedule();
+            (js_sys::Array::new(), js_sys::Array::new())
+        });
+        messages.push(message);
+        for value in transfer.iter() {
+            transferred.push(&value);
+        }
+    }
+
+    fn schedule(self: &Rc<Self>) {
+        let batch = self.clone();
+        if self.batching == Batching::AnimationFrame {
+            let request_animation_frame = js_sys::Reflect::get(&js_sys::global(), &"requestAnimationFrame".into())
+                .ok()
+                .and_then(|function| function.dyn_into::<js_sys::Function>().ok());
+            if let Some(request_animation_frame) = request_animation_frame {
+                let flush = wasm_bindgen::closure::Closure::once_into_js(move || batch.flush());
+                let _ = request_animation_frame.call1(&JsValue::UNDEFINED, &flush);
+                return;
+            }
+        }
+        wasm_bindgen_futures::spawn_local(async move {
+            batch.flush();
+        });
+    }
+
+    fn flush(&self) {
+        if let Some((messages, transferred)) = self.pending.take() {
+            /* a single message is posted as is */
+            let message = match messages.length() {
+                1 => messages.get(0),
+                _ => messages.into(),
+            };
+            self.port.post_message(&message, &transferred).unwrap();
+        }
+    }
+}
+
+/// The virtual channels of a port. A channel is identified by its id together with which side of
+/// the port opened it, since both sides allocate ids independently. The tag of a message is the id
+/// of its channel shifted left by one, with the lowest bit set if the sender accepted the channel.
+struct Channels {
+    port: crate::port::Port,
+    next_id: Cell<u32>,
+    /* the senders of the channels, keyed by their ids and whether they were opened on this side */
+    routes: RefCell<HashMap<(u32, bool), mpsc::UnboundedSender<js_sys::Array>>>,
+    /* the chunks of the messages that are being reassembled, keyed by the tags of their channels */
+    chunks: RefCell<HashMap<Option<u32>, Chunks>>,
+    max_message_size: Cell<Option<usize>>,
+    /* the flags of the compressions that the other side of the port can decompress */
+    peer_compressions: Cell<u32>,
+    accepted_tx: mpsc::UnboundedSender<(u32, mpsc::UnboundedReceiver<js_sys::Array>)>,
+    accepted_rx: RefCell<mpsc::UnboundedReceiver<(u32, mpsc::UnboundedReceiver<js_sys::Array>)>>,
+}
+
+impl Channels {
+    /// Collect the chunks of a message, returning the message once its last chunk has been
+    /// received. The chunks that precede the last one are posted on their own as `Uint8Array`s,
+    /// while the last chunk replaces the `ArrayBuffer` of the original message.
+    fn reassemble(&self, tag: Option<u32>, message: js_sys::Array) -> Option<js_sys::Array> {
+        let mut chunks = self.chunks.borrow_mut();
+        let max_message_size = self.max_message_size.get().unwrap_or(usize::MAX);
+        let first = message.get(0);
+        if let Some(chunk) = first.dyn_ref::<js_sys::Uint8Array>() {
+            let pending = chunks.entry(tag).or_default();
+            pending.size += chunk.length() as usize;
+            /* the chunks of oversized messages are discarded as they arrive */
+            match pending.size > max_message_size {
+                true => pending.chunks.clear(),
+                false => pending.chunks.push(chunk.clone()),
+            }
+            return None;
+        }
+        let index = buffer_index(&message);
+        let Some(pending) = chunks.remove(&tag) else {
+            /* messages that were not chunked are limited as well */
+            let size = message.get(index).dyn_ref::<js_sys::ArrayBuffer>()
+                .map_or(0, |buffer| buffer.byte_length() as usize);
+            return (size <= max_message_size).then_some(message);
+        };
+        let last = message.get(index).dyn_into::<js_sys::ArrayBuffer>().ok()?;
+        let size = pending.size + last.byte_length() as usize;
+        if size > max_message_size {
+            return None;
+        }
+        let buffer = js_sys::Uint8Array::new_with_length(size as u32);
+        let mut offset = 0;
+        for chunk in pending.chunks.iter().chain([&js_sys::Uint8Array::new(&last)]) {
+            buffer.set(chunk, offset);
+            offset += chunk.length();
+        }
+        message.set(index, buffer.buffer().into());
+        Some(message)
+    }
+
+    /// Decompress a message if it starts with the name of its compression
+    fn decompress(&self, message: js_sys::Array) -> Option<js_sys::Array> {
+        let Some(name) = message.get(0).as_string() else {
+            return Some(message);
+        };
+        let compression = Compression::parse(&name)?;
+        let buffer = js_sys::Uint8Array::new(&message.get(1)).to_vec();
+        let max_message_size = self.max_message_size.get().unwrap_or(usize::MAX);
+        let buffer = compression.decompress(&buffer, max_message_size)?;
+        message.shift();
+        message.set(0, js_sys::Uint8Array::from(&buffer[..]).buffer().into());
+        Some(message)
+    }
+
+    /// Forget the channel with `tag` once the other side has closed it, which ends the stream of
+    /// its messages
+    fn close(&self, tag: u32) {
+        let (id, opened_here) = (tag >> 1, tag & 1 == 1);
+        self.routes.borrow_mut().remove(&(id, opened_here));
+        self.chunks.borrow_mut().remove(&Some(tag));
+    }
+
+    /// Forward a message to the channel with `tag`, a message without contents opens a channel
+    fn route(&self, tag: u32, message: js_sys::Array) {
+        let (id, opened_here) = (tag >> 1, tag & 1 == 1);
+        if message.length() == 0 {
+            let (messages_tx, messages_rx) = mpsc::unbounded();
+            self.routes.borrow_mut().insert((id, false), messages_tx);
+            let _ = self.accepted_tx.unbounded_send((id, messages_rx));
+            return;
+        }
+        let mut routes = self.routes.borrow_mut();
+        /* forget the channels whose interfaces have been dropped */
+        if let Some(messages_tx) = routes.get(&(id, opened_here)) {
+            if messages_tx.unbounded_send(message).is_err() {
+                routes.remove(&(id, opened_here));
+            }
+        }
+    }
+}
+
+/// The sending half of an interface, this tags the messages of virtual channels and compresses,
+/// splits, and batches the messages if the interface uses compression, chunking, and batching
+#[derive(Clone)]
+pub(crate) struct Sender {
+    port: crate::port::Port,
+    tag: Option<u32>,
+    chunk_size: Option<u32>,
+    compressor: Option<Compressor>,
+    batch: Option<Rc<Batch>>,
+    /* closes the virtual channel once the last clone of the sender has been dropped */
+    _close: Option<Rc<Close>>,
+}
+
+impl Sender {
+    pub(crate) fn post_message(&self, message: &js_sys::Array, transfer: &js_sys::Array) -> Result<(), JsValue> {
+        if let Some(compressor) = &self.compressor {
+            compressor.compress(message, transfer);
+        }
+        let index = buffer_index(message);
+        let buffer = message.get(index).dyn_into::<js_sys::ArrayBuffer>();
+        match (self.chunk_size, buffer) {
+            (Some(chunk_size), Ok(buffer)) if buffer.byte_length() > chunk_size => {
+                /* the chunks are posted as separate messages rather than batched, after the messages
+                   that are pending in the batch to preserve the order of the messages */
+                if let Some(batch) = &self.batch {
+                    batch.flush();
+                }
+                let (len, mut offset) = (buffer.byte_length(), 0);
+                /* all chunks except the last one are posted on their own */
+                while len - offset > chunk_size {
+                    let chunk = buffer.slice_with_end(offset, offset + chunk_size);
+                    let (message, transfer) = (js_sys::Array::of1(&js_sys::Uint8Array::new(&chunk)), js_sys::Array::of1(&chunk));
+                    self.post_chunk(&message, &transfer, false)?;
+                    offset += chunk_size;
+                }
+                let last = buffer.slice(offset);
+                message.set(index, last.clone().into());
+                transfer.push(&last);
+                self.post_chunk(message, transfer, false)
+            },
+            _ => self.post_chunk(message, transfer, true),
+        }
+    }
+
+    /// Tag `message` and post it, adding it to the pending batch if the interface uses batching and
+    /// `batched` is set
+    fn post_chunk(&self, message: &js_sys::Array, transfer: &js_sys::Array, batched: bool) -> Result<(), JsValue> {
+        if let Some(tag) = self.tag {
+            message.unshift(&tag.into());
+        }
+        match &self.batch {
+            Some(batch) if batched => {
+                batch.push(message, transfer);
+                Ok(())
+            },
+            _ => self.port.post_message(message, transfer),
+        }
+    }
+}
+
+/// Closes a virtual channel when dropped, i.e., once this side of the channel can neither send nor
+/// receive messages anymore, and tells the other side to end the stream of the channel's messages
+struct Close {
+    tag: u32,
+    channels: Rc<Channels>,
+}
+
+impl Drop for Close {
+    fn drop(&mut self) {
+        let (id, opened_here) = (self.tag >> 1, self.tag & 1 == 0);
+        self.channels.routes.borrow_mut().remove(&(id, opened_here));
+        let message = js_sys::Array::of2(&self.tag.into(), &JsValue::NULL);
+        let _ = self.channels.port.post_message(&message, &JsValue::UNDEFINED);
+    }
+}
@@ -101,6 +101,261 @@
 //! The implication of this, however, is that even if the server method is asynchronous, we are unable
 //! to cancel it from the client side since we do not have a future that can be dropped.
 //! 
+//! Notifications that need to be confirmed or cancelled can be annotated with `#[ack]` or
+//! `#[abortable]`. The client method of an acknowledged notification returns a future that resolves
+//! once the server has finished executing the method, while the client method of an abortable
+//! notification returns a [`client::NotificationHandle`] that aborts the notification when dropped:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Renderer {
+//!     #[ack]
+//!     async fn flush();
+//!     #[abortable]
+//!     async fn animate(frames: u32);
+//! }
+//! client.flush().await;
+//! let animation = client.animate(60);
+//! /* stop the animation */
+//! drop(animation);
+//! ```
+//! These notifications are sent as requests, i.e., they are limited by the number of requests in
+//! flight and can be intercepted.
+//! 
+//! ### Execution policies
+//! By default, the requests to asynchronous methods are executed concurrently and may complete in any
+//! order. This can be changed with [`Builder::with_execution`], which allows requests to be executed
+//! sequentially or in the order in which they were received per order key. The order key is the argument
+//! of a method annotated with `#[order_key]`:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Documents {
+//!     async fn write(#[order_key] document: u32, contents: String);
+//! }
+//! /* writes to the same document are applied in the order in which they were sent */
+//! let server = web_rpc::Builder::new(server_interface)
+//!     .with_service::<DocumentsService<_>>(DocumentsServiceImpl)
+//!     .with_execution(web_rpc::server::Execution::Keyed)
+//!     .build();
+//! ```
+//! ### Limits and backpressure
+//! The number of requests that a server executes at the same time can be limited globally using
+//! [`Builder::with_max_in_flight`] and per method using [`Builder::with_method_limit`]. Requests that
+//! exceed these limits are queued. The size of the queue is set with [`Builder::with_queue_capacity`]
+//! and [`Builder::with_overflow`] decides whether new requests wait for space in the queue or are
+//! rejected with [`Error::Overloaded`]. Clients can handle such errors by converting the returned
+//! future using [`client::RequestFuture::into_result`]:
+//! ```rust
+//! match client.render(scene).into_result().await {
+//!     Ok(frame) => display(frame),
+//!     Err(web_rpc::Error::Overloaded) => skip_frame(),
+//!     Err(error) => log_error(error),
+//! }
+//! ```
+//! Clients can also limit the number of requests that they have in flight using
+//! [`Builder::with_client_max_in_flight`]. Depending on [`Builder::with_client_overflow`], further
+//! calls then either wait until a request completes before sending their request or fail immediately
+//! with [`Error::Busy`]:
+//! ```rust
+//! let client = web_rpc::Builder::new(client_interface)
+//!     .with_client::<RendererClient>()
+//!     .with_client_max_in_flight(4)
+//!     .with_client_overflow(web_rpc::Overflow::Reject)
+//!     .build();
+//! ```
+//! ### Graceful shutdown
+//! Dropping a server stops it immediately. To shut it down gracefully, get a [`server::ServerHandle`]
+//! using [`Server::handle`] before spawning the server and call [`server::ServerHandle::shutdown`]
+//! with a timeout:
+//! ```rust
+//! let server = web_rpc::Builder::new(server_interface)
+//!     .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+//!     .build();
+//! let server_handle = server.handle();
+//! wasm_bindgen_futures::spawn_local(server);
+//! /* ... */
+//! server_handle.shutdown(Duration::from_secs(1)).await;
+//! ```
+//! The server then stops accepting requests, gives the running requests until the timeout to complete,
+//! and aborts the rest. Queued requests, requests that were still running, and any later calls on the
+//! client fail with [`Error::Shutdown`]. The server future completes once the shutdown is done.
+//! ### Statistics
+//! Servers and clients keep statistics that can be used for diagnostics, such as finding out which
+//! worker is backed up. [`Server::stats`] and [`server::ServerHandle::stats`] return the requests that
+//! are running and queued, the total number of requests per method, aborts, errors, and the number
+//! of bytes sent and received. The generated clients have a `stats` method that returns similar
+//! statistics for the client:
+//! ```rust
+//! let server_stats = server_handle.stats();
+//! let client_stats = client.stats();
+//! log(format!("{} requests queued, {} requests in flight", server_stats.queued, client_stats.in_flight));
+//! ```
+//! ### Layers
+//! Cross-cutting behavior such as logging, authorization, or metrics can be added to a server without
+//! changing the implementation of the service by using layers. A layer implements [`server::Layer`]
+//! and is added with [`Builder::with_layer`]. It receives the method name and sequence id of a request
+//! together with the decoded request and decides whether to run the next layer, in which case it also
+//! has access to the response, or to fail the request, e.g., with [`Error::Rejected`]:
+//! ```rust
+//! struct Auth;
+//! impl Layer<VaultRequest, VaultResponse> for Auth {
+//!     fn call<'a>(
+//!         &'a self,
+//!         context: Context,
+//!         request: VaultRequest,
+//!         next: Next<'a, VaultRequest, VaultResponse>
+//!     ) -> LocalBoxFuture<'a, Result<VaultResponse, web_rpc::Error>> {
+//!         Box::pin(async move {
+//!             match context.method {
+//!                 "delete" => Err(web_rpc::Error::Rejected(String::from("access denied"))),
+//!                 _ => next.run(request).await,
+//!             }
+//!         })
+//!     }
+//! }
+//! let server = web_rpc::Builder::new(server_interface)
+//!     .with_service::<VaultService<_>>(VaultServiceImpl)
+//!     .with_layer(Auth)
+//!     .build();
+//! ```
+//! Layers can be stacked by calling [`Builder::with_layer`] multiple times, the layer that was added
+//! last is called first.
+//! ### Interceptors
+//! The client side counterpart of layers are interceptors, which implement [`client::Interceptor`] and
+//! are added with [`Builder::with_interceptor`]. An interceptor receives the method name and the decoded
+//! request of each call and can return a response or an error without sending the request, e.g., from
+//! a cache, or run the next interceptor one or more times to send the request, e.g., to retry it. After
+//! sending a request, [`client::Next::sent`] provides its sequence id and encoded size:
+//! ```rust
+//! struct Log;
+//! impl<Request: 'static, Response: 'static> Interceptor<Request, Response> for Log {
+//!     fn call<'a>(
+//!         &'a self,
+//!         context: Context,
+//!         request: Request,
+//!         next: Next<'a, Request, Response>
+//!     ) -> LocalBoxFuture<'a, Result<Response, web_rpc::Error>> {
+//!         Box::pin(async move {
+//!             let start = js_sys::Date::now();
+//!             let response = next.run(request).await;
+//!             let latency = js_sys::Date::now() - start;
+//!             if let Some(sent) = next.sent() {
+//!                 log(format!("{} sent {} bytes, took {latency}ms", context.method, sent.size));
+//!             }
+//!             response
+//!         })
+//!     }
+//! }
+//! let client = web_rpc::Builder::new(client_interface)
+//!     .with_client::<CalculatorClient>()
+//!     .with_interceptor(Log)
+//!     .build();
+//! ```
+//! Note that retrying a request requires cloning it, which can be enabled by adding `derive(Clone)`
+//! to the [`macro@service`] attribute. Also note that the responses of methods whose return values
+//! are posted or remote can only be received from the server, returning such a response without
+//! sending the request fails the call with [`Error::Rejected`].
+//! ### Metadata
+//! Requests and responses can carry [`Metadata`], a map of strings for per-call context such as
+//! authentication tokens, trace ids, or locales. The generated clients have a `with_metadata` method
+//! that returns a client sending the given entry with every request and notification, which can be
+//! used both for a long-lived client and for a single call. Inside the server, the metadata of the
+//! request that is being executed is available via [`RequestContext::current`]:
+//! ```rust
+//! impl Calculator for CalculatorServiceImpl {
+//!     fn add(&self, left: u32, right: u32) -> u32 {
+//!         let context = web_rpc::RequestContext::current().unwrap();
+//!         if let Some(trace_id) = context.metadata().get("trace-id") {
+//!             context.set_response_metadata("trace-id", trace_id.clone());
+//!         }
+//!         left + right
+//!     }
+//! }
+//! let client = client.with_metadata("trace-id", "abc");
+//! assert_eq!(client.add(41, 1).await, 42);
+//! ```
+//! Interceptors can modify the metadata of a request using [`client::Next::metadata`] and read the
+//! metadata of its response using [`client::Next::response_metadata`].
+//! ### Request context and timeouts
+//! The generated clients also have a `with_timeout` method that returns a client whose requests are
+//! aborted and fail with [`Error::Timeout`] if their responses are not received in time. The timeout is
+//! sent to the server, where it is available together with the sequence id, the metadata, and whether
+//! the request has been cancelled from the [`RequestContext`] of the request. Instead of calling
+//! [`RequestContext::current`], services that are generated with the `context` argument receive the
+//! context as the first parameter of their methods:
+//! ```rust
+//! #[web_rpc::service(context)]
+//! pub trait Search {
+//!     async fn search(query: String) -> Vec<String>;
+//! }
+//! impl Search for SearchServiceImpl {
+//!     async fn search(&self, ctx: &web_rpc::RequestContext, query: String) -> Vec<String> {
+//!         let mut results = Vec::new();
+//!         for index in &self.indices {
+//!             /* return the partial results if the deadline is close */
+//!             if ctx.remaining().is_some_and(|remaining| remaining < Duration::from_millis(10)) {
+//!                 break;
+//!             }
+//!             results.extend(index.search(&query).await);
+//!         }
+//!         results
+//!     }
+//! }
+//! let results = client.with_timeout(Duration::from_millis(100)).search(query).await;
+//! ```
+//! The future returned by [`RequestContext::cancelled`] resolves once the client aborts the request or
+//! the server aborts it while shutting down, which is useful for tasks that outlive the request.
+//! ### Cancellation
+//! Dropping a request's future aborts the request, in which case the server drops the future of the
+//! method without giving it a chance to clean up. Alternatively, [`client::RequestFuture::cancel`]
+//! cancels a request while still waiting for its response. Async methods of services that are
+//! generated with the `cooperative` argument receive a [`CancellationToken`] after the context and
+//! are not dropped when their requests are cancelled. Instead, they can clean up and return a partial
+//! result:
+//! ```rust
+//! #[web_rpc::service(cooperative)]
+//! pub trait Import {
+//!     async fn import(items: Vec<Item>) -> usize;
+//! }
+//! impl Import for ImportServiceImpl {
+//!     async fn import(&self, token: &web_rpc::CancellationToken, items: Vec<Item>) -> usize {
+//!         let (transaction, count) = (self.database.transaction(), items.len());
+//!         for (index, item) in items.into_iter().enumerate() {
+//!             if token.is_cancelled() {
+//!                 transaction.commit().await;
+//!                 return index;
+//!             }
+//!             transaction.put(item).await;
+//!         }
+//!         transaction.commit().await;
+//!         count
+//!     }
+//! }
+//! let import = client.import(items);
+//! cancel_button.clicked().await;
+//! import.cancel();
+//! let imported = import.await;
+//! ```
+//! Cancelled requests to methods that are not cooperative fail with [`Error::Cancelled`].
+//! ### Actors
+//! Services that need to modify their state across `.await` points can be generated with the `actor`
+//! argument. In this case, the methods of the trait take `&mut self` and the server executes the requests
+//! one at a time, queuing the requests that arrive while another request is being executed:
+//! ```rust
+//! #[web_rpc::service(actor)]
+//! pub trait Counter {
+//!     async fn increment(interval: Duration) -> u32;
+//! }
+//!
+//! struct CounterServiceImpl { count: u32 }
+//! impl Counter for CounterServiceImpl {
+//!     async fn increment(&mut self, interval: Duration) -> u32 {
+//!         gloo_timers::future::sleep(interval).await;
+//!         self.count += 1;
+//!         self.count
+//!     }
+//! }
+//! ```
+//! The number of queued requests can be limited using [`Builder::with_queue_capacity`].
 //! ### Posting and transferring Javascript types
 //! In the example above, we discussed how the client serializes its arguments before sending them to 
 //! the server. This approach is convenient, but how do send web types such as a `WebAssembly.Module`
@@ -131,6 +386,128 @@
 //!     );
 //! }
 //! ```
+//! Posted arguments and return values can also be an `Option` or a `Vec` of web types, in which case
+//! the number of values is serialized and each value is posted or transferred separately:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Textures {
+//!     #[post(transfer(bitmaps), return)]
+//!     fn upload(bitmaps: Vec<web_sys::ImageBitmap>) -> Option<js_sys::JsString>;
+//! }
+//! ```
+//! Posting only applies to whole arguments and return values. For types that mix serialized fields with
+//! web types, [`Postable`](macro@Postable) can be derived instead. The fields annotated with `#[post]`
+//! or `#[post(transfer)]` are then posted or transferred, while the other fields are serialized:
+//! ```rust
+//! #[derive(web_rpc::Postable)]
+//! pub struct Frame {
+//!     width: u32,
+//!     height: u32,
+//!     #[post(transfer)]
+//!     bitmap: web_sys::ImageBitmap,
+//! }
+//! #[web_rpc::service]
+//! pub trait Renderer {
+//!     fn render(scene: Scene) -> Frame;
+//!     fn draw(frames: Vec<Frame>);
+//! }
+//! ```
+//! These types do not need to be listed in the `post` attribute and can also be used inside other
+//! serialized types such as `Option` or `Vec`.
+//!
+//! Large byte buffers can be sent without serializing them by using [`Bytes`]. The bytes are
+//! transferred as their own `ArrayBuffer`, so they are copied at most once when they are sent from
+//! Rust memory and not at all when they were received or created from an `ArrayBuffer`. Received
+//! bytes stay in Javascript memory until they are converted with [`Bytes::into_vec`]:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Storage {
+//!     fn read(path: String) -> web_rpc::Bytes;
+//!     fn write(path: String, contents: web_rpc::Bytes);
+//! }
+//! ```
+//! ### Remote objects
+//! Methods can return or accept handles to objects that are served on their own channels. A [`Remote`]
+//! is created from the implementation of a service, which is then served on a new channel that is
+//! transferred alongside the request or the response. On the other side, the client of the object is
+//! obtained from the remote. The arguments and return values that are remotes are listed in the
+//! `remote` attribute of their method:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait FileSystem {
+//!     #[remote(return)]
+//!     fn open(name: String) -> web_rpc::Remote<FileClient>;
+//! }
+//! impl FileSystem for FileSystemServiceImpl {
+//!     fn open(&self, name: String) -> web_rpc::Remote<FileClient> {
+//!         web_rpc::Remote::new::<FileService<_>>(FileServiceImpl::new(name))
+//!     }
+//! }
+//! /* ... */
+//! let file = client.open(String::from("a.txt")).await.into_client();
+//! let contents = file.read().await;
+//! ```
+//! The object is dropped once the remote and all clones of its client have been dropped. Remotes do
+//! not need to be listed in the `post` attribute.
+//! ### Callbacks
+//! Methods can also accept callbacks, which allow a service to push events back to its caller without
+//! defining a second service in the reverse direction. A [`Callback`] is created from a closure that
+//! stays in the calling context and is run each time the service calls the callback. Like remotes,
+//! callbacks are listed in the `remote` attribute of their method:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Watcher {
+//!     #[remote(on_change)]
+//!     fn watch(path: String, on_change: web_rpc::Callback<ChangeEvent>);
+//! }
+//! impl Watcher for WatcherServiceImpl {
+//!     fn watch(&self, path: String, on_change: web_rpc::Callback<ChangeEvent>) {
+//!         on_change.call(ChangeEvent::new(path));
+//!     }
+//! }
+//! /* ... */
+//! client.watch(path, web_rpc::Callback::new(|event: ChangeEvent| {
+//!     /* handle the event */
+//! }));
+//! ```
+//! The values that a callback is called with are serialized and the closure is dropped once the service
+//! has dropped the callback.
+//! ### Composing services
+//! A service can extend other services by listing them as supertraits. The generated client then
+//! provides the methods of its supertraits and the generated service dispatches the requests for the
+//! supertraits' methods to the corresponding implementations. The methods of a supertrait are provided
+//! by a generated trait, e.g., `HealthClientMethods` for `Health`, which needs to be in scope:
+//! ```rust
+//! #[web_rpc::service]
+//! pub trait Admin: Health + Metrics {
+//!     fn restart();
+//! }
+//! /* ... */
+//! use health::HealthClientMethods;
+//! let healthy = admin_client.is_healthy().await;
+//! ```
+//! The clients of the supertraits can also be accessed directly, e.g., using `admin_client.health()`.
+//! Only the methods of the direct supertraits are provided, i.e., if `Health` itself extends `Base`,
+//! the methods of `Base` are called via `admin_client.health()` unless `Base` is also listed as a
+//! supertrait of `Admin`. Note that the supertraits must also be annotated with the
+//! [`macro@service`] macro and that the names of the types generated for the supertraits must not
+//! have been changed, i.e., a supertrait cannot use the `client`, `service`, `request`, or
+//! `response` arguments of the macro.
+//! ### Configuring the generated items
+//! The names of the generated types can be changed by passing arguments to the [`macro@service`]
+//! macro. It is also possible to add derives to the request and response types (e.g., for logging)
+//! and to specify the path to this crate when it is re-exported from another crate:
+//! ```rust
+//! #[my_facade::rpc::service(
+//!     client = "Calculator",
+//!     service = "CalculatorServer",
+//!     derive(Debug, Clone),
+//!     crate = "my_facade::rpc"
+//! )]
+//! pub trait CalculatorApi {
+//!     fn add(left: u32, right: u32) -> u32;
+//! }
+//! ```
 //! ### Bi-directional RPC
 //! In the original example, we created a server on the first port of the message channel and a client on
 //! the second port. However, it is possible to define both a client and a server on each side, enabling
@@ -155,14 +532,70 @@
 //!     .with_client::<CalculatorClient>()
 //!     .build();
 //! ```
+//! ### Virtual channels
+//! Creating a new `MessageChannel` for every conversation requires a handshake and a port each time.
+//! Instead, an interface can multiplex lightweight virtual channels over its port.
+//! [`Interface::open_channel`] returns an interface for a new channel, which the other side receives
+//! from [`Interface::accept_channel`]. These interfaces are used with the [`Builder`] like any other
+//! interface:
+//! ```rust
+//! /* on one side of the port */
+//! let channel = interface.open_channel();
+//! let client = web_rpc::Builder::new(channel)
+//!     .with_client::<CalculatorClient>()
+//!     .build();
+//! /* on the other side of the port */
+//! let channel = interface.accept_channel().await;
+//! let server = web_rpc::Builder::new(channel)
+//!     .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+//!     .build();
+//! ```
+//! Channels can be opened from both sides and the interface that was used to open or accept them can
+//! still be passed to the [`Builder`] afterwards. A channel is closed once its side of the channel has
+//! been dropped, i.e., the interface or the client and the server that were built from it, in which
+//! case the requests of the client on the other side fail with [`Error::Shutdown`] and the server on
+//! the other side aborts its running requests and completes.
+//! ### Batching
+//! Every request and response is posted as its own message by default, which can dominate the cost
+//! of issuing many small calls. With [`Interface::with_batching`], the messages that an interface
+//! sends within the same turn of the event loop are coalesced into a single posted message, either
+//! in a microtask or before the next animation frame, see [`interface::Batching`]:
+//! ```rust
+//! let client = web_rpc::Builder::new(interface.with_batching(web_rpc::interface::Batching::Microtask))
+//!     .with_client::<CalculatorClient>()
+//!     .build();
+//! let sums = futures_util::future::join_all((0..100).map(|value| client.add(value, value))).await;
+//! ```
+//! Batches are unbatched on the other side regardless of whether its interface uses batching, so a
+//! server that uses batching sends its responses in batches even to clients that do not.
+//! ### Chunking
+//! Some transports limit the size of messages and huge messages can stall the event loop of the
+//! receiver. [`Interface::with_chunk_size`] splits the encoded messages that an interface sends into
+//! chunks, which are posted separately even if the interface uses batching and are reassembled on the
+//! other side. The receiver can limit the
+//! size of the messages, including reassembled ones, with [`Interface::with_max_message_size`],
+//! messages that exceed this size are discarded:
+//! ```rust
+//! let interface = interface
+//!     .with_chunk_size(16 * 1024)
+//!     .with_max_message_size(16 * 1024 * 1024);
+//! ```
+//! ### Compression
+//! Large messages such as documents can be compressed with [`Interface::with_compression`], which
+//! compresses the encoded messages that exceed a threshold before they are chunked and posted. The
+//! algorithms of [`interface::Compression`] are enabled with the `lz4` and `deflate` cargo features.
+//! Both sides of a port announce the algorithms that they can decompress during the handshake of
+//! [`Interface::new`], so messages are only compressed if the other side was built with the same
+//! feature:
+//! ```rust
+//! let interface = interface.with_compression(web_rpc::interface::Compression::Lz4, 4 * 1024);
+//! ```
 
-use std::{cell::RefCell, marker::PhantomData, pin::Pin, rc::Rc, task::{Context, Poll}};
+use std::{cell::{Cell, RefCell}, marker::PhantomData, rc::Rc, time::Duration};
 
 use futures_channel::mpsc;
-use futures_core::{future::LocalBoxFuture, Future};
 use futures_util::{FutureExt, StreamExt};
-use gloo_events::EventListener;
-use js_sys::{Uint8Array, ArrayBuffer};
+use js_sys::ArrayBuffer;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use wasm_bindgen::JsCast;
 
@@ -185,31 +618,118 @@ pub use serde;
 #[doc(hidden)]
 pub use wasm_bindgen;
 
-pub use web_rpc_macro::service;
+pub use web_rpc_macro::{service, Postable};
+
+#[cfg(feature = "copy-stats")]
+#[doc(hidden)]
+pub use codec::{copied, Copied};
 
+mod bytes;
+mod callback;
 pub mod client;
+mod codec;
+mod compression;
+mod context;
 #[doc(hidden)]
 pub mod service;
 pub mod interface;
 pub mod port;
+#[doc(hidden)]
+pub mod post;
+mod remote;
+pub mod server;
 
+pub use bytes::Bytes;
+pub use callback::Callback;
+pub use context::{CancellationToken, Metadata, RequestContext};
 pub use interface::Interface;
+pub use remote::Remote;
+pub use server::Server;
 
 #[doc(hidden)]
 #[derive(Serialize, Deserialize)]
 pub enum Message<Request, Response> {
-    Request(usize, Request),
+    Request(usize, Metadata, Option<Duration>, Request),
     Abort(usize),
-    Response(usize, Response),
+    Cancel(usize),
+    Response(usize, Metadata, Response),
+    Error(usize, Error),
+    Shutdown,
+}
+
+impl<Request: Serialize, Response: Serialize> Message<Request, Response> {
+    /// Serialize this message and post it together with the posted and transferred arguments,
+    /// returning the number of serialized bytes
+    pub(crate) fn post(&self, port: &interface::Sender, post_args: js_sys::Array, transfer_args: js_sys::Array) -> usize {
+        /* the values posted by the serialized message precede the posted arguments */
+        let (buffer, posted, transferred) = codec::encode(self);
+        let (post_args, transfer_args) = (posted.concat(&post_args), transferred.concat(&transfer_args));
+        post_args.unshift(&buffer);
+        transfer_args.unshift(&buffer);
+        port.post_message(&post_args, &transfer_args).unwrap();
+        buffer.byte_length() as usize
+    }
+}
+
+/// The errors that can cause a RPC request to fail. Awaiting a [`client::RequestFuture`]
+/// panics with these errors, use [`client::RequestFuture::into_result`] to handle them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Error {
+    /// The server rejected the request since it had too many requests in flight
+    /// and its queue was full.
+    Overloaded,
+    /// The client rejected the request since it had too many requests in flight.
+    Busy,
+    /// The server shut down before the request completed or the request was made after
+    /// the server shut down.
+    Shutdown,
+    /// A layer of the server rejected the request for the given reason, see [`server::Layer`].
+    Rejected(String),
+    /// The response was not received before the timeout of the client elapsed.
+    Timeout,
+    /// The request was cancelled using [`client::RequestFuture::cancel`] and the method
+    /// did not return a partial result.
+    Cancelled,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Overloaded => formatter.write_str("the server is overloaded"),
+            Error::Busy => formatter.write_str("the client has too many requests in flight"),
+            Error::Shutdown => formatter.write_str("the server has shut down"),
+            Error::Rejected(reason) => write!(formatter, "the request was rejected: {reason}"),
+            Error::Timeout => formatter.write_str("the request timed out"),
+            Error::Cancelled => formatter.write_str("the request was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// What happens to the requests that exceed the limits of a client or a server, see
+/// [`Builder::with_client_overflow`] and [`Builder::with_overflow`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The request waits for one of the requests in flight to complete. A client waits before
+    /// sending the request, while a server holds the request back until a queued request has been
+    /// started. This is the default behavior.
+    #[default]
+    Wait,
+    /// The request fails immediately, with [`Error::Busy`] if it was rejected by the client and
+    /// with [`Error::Overloaded`] if it was rejected by the server.
+    Reject,
 }
 
 /// This struct allows one to configure the RPC interface prior to creating it.
 /// To get an instance of this struct, call [`Builder<C, S>::new`] with
 /// an [`Interface`].
-pub struct Builder<C, S> {
+pub struct Builder<C: client::Client, S> {
     client: PhantomData<C>,
     service: S,
     interface: Interface,
+    server_options: server::Options,
+    client_options: client::Options<C::Request, C::Response>,
 }
 
 impl Builder<(), ()> {
@@ -219,11 +739,13 @@ impl Builder<(), ()> {
             interface,
             client: PhantomData::<()>,
             service: (),
+            server_options: Default::default(),
+            client_options: Default::default(),
         }
     }
 }
 
-impl<C> Builder<C, ()> {
+impl<C: client::Client> Builder<C, ()> {
     /// Configure the RPC interface with a service that implements methods
     /// that can be called from the other side of the channel. To use this method,
     /// you need to specify the type `S` which is the service type generated by the
@@ -243,8 +765,8 @@ impl<C> Builder<C, ()> {
         implementation: impl Into<S>
     ) -> Builder<C, S> {
         let service = implementation.into();
-        let Builder { interface, client, .. } = self;
-        Builder { interface, client, service }
+        let Builder { interface, client, server_options, client_options, .. } = self;
+        Builder { interface, client, service, server_options, client_options }
     }
 }
 
@@ -258,28 +780,91 @@ impl<S> Builder<(), S> {
     pub fn with_client<C: client::Client>(
         self,
     ) -> Builder<C, S> {
-        let Builder { interface, service, .. } = self;
-        Builder { interface, client: PhantomData::<C>, service }
+        /* the client options can only be set once the builder has a client */
+        let Builder { interface, service, server_options, .. } = self;
+        Builder { interface, client: PhantomData::<C>, service, server_options, client_options: Default::default() }
     }
 }
 
-/// `Server` is the server that is returned from the [`Builder::build`] method given
-/// you configured the RPC interface with a service. Note that `Server` implements future and needs
-/// to be polled in order to execute and respond to inbound RPC requests.
-#[must_use = "Server must be polled in order for RPC requests to be executed"]
-pub struct Server {
-    _listener: Rc<EventListener>,
-    task: LocalBoxFuture<'static, ()>,
+impl<C: client::Client, S> Builder<C, S> {
+    /// Limit the number of requests that the client has in flight, i.e., the requests that
+    /// have been sent and whose responses have not been received. What happens to further calls
+    /// is set using [`Builder::with_client_overflow`]. Note that notifications are not limited
+    /// unless they are acknowledged or abortable.
+    pub fn with_client_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.client_options.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Set what happens to calls that exceed the limit of requests in flight, see [`Overflow`].
+    /// By default, the calls wait until a request completes before sending their requests.
+    pub fn with_client_overflow(mut self, overflow: Overflow) -> Self {
+        self.client_options.overflow = overflow;
+        self
+    }
+
+    /// Add an interceptor to the client, see [`client::Interceptor`]. Interceptors that are
+    /// added later wrap the interceptors that were added before, i.e., the interceptor that was
+    /// added last is called first.
+    pub fn with_interceptor<I>(mut self, interceptor: I) -> Self where
+        I: client::Interceptor<C::Request, C::Response> + 'static,
+        C::Request: 'static,
+        C::Response: 'static {
+        self.client_options.interceptors.push(Rc::new(interceptor));
+        self
+    }
 }
 
-impl Future for Server {
-    type Output = ();
+impl<C: client::Client, S: service::Service> Builder<C, S> {
+    /// Set the policy that the server uses for executing requests, see [`server::Execution`]
+    /// for the available policies. By default, requests are executed concurrently.
+    pub fn with_execution(mut self, execution: server::Execution) -> Self {
+        self.server_options.execution = execution;
+        self
+    }
+
+    /// Limit the number of requests that the server executes at the same time. Requests that
+    /// exceed this limit are queued until a running request completes.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.server_options.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    /// Limit the number of requests to `method` that the server executes at the same time.
+    /// Requests that exceed this limit are queued until a running request to `method` completes.
+    /// This panics if the service has no method named `method`.
+    pub fn with_method_limit(mut self, method: &'static str, max_in_flight: usize) -> Self {
+        assert!(S::methods().contains(&method), "the service has no method named `{method}`");
+        self.server_options.method_limits.insert(method, max_in_flight);
+        self
+    }
 
-    fn poll(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>
-    ) -> Poll<Self::Output> {
-        self.task.poll_unpin(cx)
+    /// Wrap the service in a layer, see [`server::Layer`]. Layers that are added later wrap the
+    /// layers that were added before, i.e., the layer that was added last is called first.
+    pub fn with_layer<L>(self, layer: L) -> Builder<C, service::Layered<S, L>> where
+        L: server::Layer<S::Request, S::Response> {
+        let Builder { interface, client, service, server_options, client_options } = self;
+        let service = service::Layered { service, layer };
+        Builder { interface, client, service, server_options, client_options }
+    }
+
+    /// Set what happens to requests that arrive once the queue is full, see [`Overflow`].
+    /// By default, the server holds the requests back until a queued request has been started.
+    /// Note that the server keeps receiving messages from the interface in the meantime, e.g.,
+    /// to abort requests or to deliver the responses to the client of a bi-directional interface,
+    /// so the requests that are held back are still kept in memory.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.server_options.overflow = overflow;
+        self
+    }
+
+    /// Limit the number of requests that the server queues. Requests are queued when they cannot
+    /// be executed immediately, e.g., when the service was generated with the `actor` argument of
+    /// the [`macro@service`] macro and is already executing a request. What happens once the queue
+    /// is full is set using [`Builder::with_overflow`]. By default, the queue is unbounded.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.server_options.queue_capacity = Some(capacity);
+        self
     }
 }
 
@@ -290,43 +875,81 @@ impl<C> Builder<C, ()> where
 
     /// Build function for client-only RPC interfaces.
     pub fn build(self) -> C {
-        let Builder { interface: Interface { port, listener, mut messages_rx }, ..} = self;
+        let Builder { client_options, interface: Interface { port, listener, mut messages_rx, .. }, ..} = self;
         let client_callback_map: Rc<RefCell<client::CallbackMap<C::Response>>> = Default::default();
         let client_callback_map_cloned = client_callback_map.clone();
+        let shutdown: Rc<Cell<bool>> = Default::default();
+        let shutdown_cloned = shutdown.clone();
+        let client_stats: Rc<RefCell<client::Stats>> = Default::default();
+        let client_stats_cloned = client_stats.clone();
         let dispatcher = async move {
             while let Some(array) = messages_rx.next().await {
-                let message = Uint8Array::new(&array.shift().dyn_into::<ArrayBuffer>().unwrap())
-                    .to_vec();
-                match bincode::deserialize::<Message<(), C::Response>>(&message).unwrap() {
-                    Message::Response(seq_id, response) => {
-                        if let Some(callback_tx) = client_callback_map_cloned.borrow_mut().remove(&seq_id) {
-                            let _ = callback_tx.send((response, array));
+                let message = array.shift().dyn_into::<ArrayBuffer>().unwrap();
+                client_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                match codec::decode::<Message<(), C::Response>>(&message, &array).unwrap() {
+                    Message::Response(seq_id, metadata, response) => {
+                        let callback = client_callback_map_cloned.borrow_mut().remove(&seq_id);
+                        if let Some(callback) = callback {
+                            callback(Ok((response, metadata, array)));
+                        }
+                    },
+                    Message::Error(seq_id, error) => {
+                        let callback = client_callback_map_cloned.borrow_mut().remove(&seq_id);
+                        if let Some(callback) = callback {
+                            callback(Err(error));
+                        }
+                    },
+                    Message::Shutdown => {
+                        shutdown_cloned.set(true);
+                        let callbacks = std::mem::take(&mut *client_callback_map_cloned.borrow_mut());
+                        for (_, callback) in callbacks {
+                            callback(Err(Error::Shutdown));
                         }
                     },
                     _ => panic!("client received a server message"),
                 }
             }
+            /* the other side has closed the virtual channel */
+            shutdown_cloned.set(true);
+            let callbacks = std::mem::take(&mut *client_callback_map_cloned.borrow_mut());
+            for (_, callback) in callbacks {
+                callback(Err(Error::Shutdown));
+            }
         }.boxed_local().shared();
         let port_cloned = port.clone();
+        let client_callback_map_cloned = client_callback_map.clone();
+        let client_stats_cloned = client_stats.clone();
         let abort_sender = move |seq_id: usize| {
-            let abort = Message::<C::Request, ()>::Abort(seq_id);
-            let abort = bincode::serialize(&abort).unwrap();
-            let buffer = js_sys::Uint8Array::from(&abort[..]).buffer();
-            let post_args = js_sys::Array::of1(&buffer);
-            let transfer_args = js_sys::Array::of1(&buffer);
-            port_cloned.post_message(&post_args, &transfer_args).unwrap();
+            client_callback_map_cloned.borrow_mut().remove(&seq_id);
+            let bytes_sent = Message::<C::Request, ()>::Abort(seq_id)
+                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+            client_stats_cloned.borrow_mut().bytes_sent += bytes_sent as u64;
+        };
+        let port_cloned = port.clone();
+        let client_stats_cloned = client_stats.clone();
+        let cancel_sender = move |seq_id: usize| {
+            let bytes_sent = Message::<C::Request, ()>::Cancel(seq_id)
+                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+            client_stats_cloned.borrow_mut().bytes_sent += bytes_sent as u64;
         };
-        let request_serializer = |seq_id: usize, request: C::Request| {
-            let request = Message::<C::Request, ()>::Request(seq_id, request);
-            bincode::serialize(&request).unwrap()
+        let request_serializer = |seq_id: usize, metadata: Metadata, timeout: Option<Duration>, request: C::Request| {
+            let request = Message::<C::Request, ()>::Request(seq_id, metadata, timeout, request);
+            codec::encode(&request)
         };
-        C::from((
-            client_callback_map,
+        let register_callback = move |seq_id: usize, callback: client::ResponseCallback<C::Response>| {
+            client_callback_map.borrow_mut().insert(seq_id, callback);
+        };
+        C::from(client::Configuration::new(
             port,
-            Rc::new(listener),
+            listener,
             dispatcher,
             Rc::new(request_serializer),
-            Rc::new(abort_sender)
+            Rc::new(register_callback),
+            Rc::new(abort_sender),
+            Rc::new(cancel_sender),
+            shutdown,
+            client_stats,
+            client_options,
         ))
     }
 }
@@ -338,31 +961,55 @@ impl<S> Builder<(), S> where
 
     /// Build function for server-only RPC interfaces.
     pub fn build(self) -> Server {
-        let Builder { service, interface: Interface { port, listener, mut messages_rx }, .. } = self;
+        let Builder { service, server_options, interface: Interface { port, listener, mut messages_rx, .. }, .. } = self;
         let (server_requests_tx, server_requests_rx) = mpsc::unbounded();
         let (abort_requests_tx, abort_requests_rx) = mpsc::unbounded();
+        let (shutdown_tx, shutdown_rx) = mpsc::unbounded();
+        let server_stats: Rc<RefCell<server::Stats>> = Default::default();
+        let server_stats_cloned = server_stats.clone();
+        let port_cloned = port.clone();
         let dispatcher = async move {
             while let Some(array) = messages_rx.next().await {
-                let message = Uint8Array::new(&array.shift().dyn_into::<ArrayBuffer>().unwrap())
-                    .to_vec();
-                match bincode::deserialize::<Message<S::Request, ()>>(&message).unwrap() {
-                    Message::Request(seq_id, request) =>
-                        server_requests_tx.unbounded_send((seq_id, request, array)).unwrap(),
-                    Message::Abort(seq_id) =>
-                        abort_requests_tx.unbounded_send(seq_id).unwrap(),
-                    _ => panic!("server received a client message"),
+                let message = array.shift().dyn_into::<ArrayBuffer>().unwrap();
+                server_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                match codec::decode::<Message<S::Request, ()>>(&message, &array).unwrap() {
+                    Message::Request(seq_id, metadata, timeout, request) => {
+                        let context = RequestContext::new(seq_id, metadata, timeout);
+                        /* the requests that arrive after the server has shut down are rejected */
+                        if server_requests_tx.unbounded_send((context, request, array)).is_err() {
+                            let bytes_sent = Message::<(), S::Response>::Error(seq_id, Error::Shutdown)
+                                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+                            let mut server_stats = server_stats_cloned.borrow_mut();
+                            server_stats.bytes_sent += bytes_sent as u64;
+                            server_stats.errors += 1;
+                        }
+                    },
+                    /* the aborts that arrive after the server has shut down are ignored */
+                    Message::Abort(seq_id) => {
+                        let _ = abort_requests_tx.unbounded_send((seq_id, false));
+                    },
+                    Message::Cancel(seq_id) => {
+                        let _ = abort_requests_tx.unbounded_send((seq_id, true));
+                    },
+                    Message::Response(..) | Message::Error(..) | Message::Shutdown =>
+                        panic!("server received a client message"),
                 }
             }
         }.boxed_local().shared();
         Server {
-            _listener: Rc::new(listener),
+            _listener: listener,
             task: service::task::<S, ()>(
                 service,
                 port,
                 dispatcher,
                 server_requests_rx,
-                abort_requests_rx
-            ).boxed_local()
+                abort_requests_rx,
+                shutdown_rx,
+                server_stats.clone(),
+                server_options
+            ).boxed_local(),
+            shutdown_tx,
+            stats: server_stats,
         }
     }
 }
@@ -377,49 +1024,109 @@ impl<C, S> Builder<C, S> where
     
     /// Build function for client-server RPC interfaces.
     pub fn build(self) -> (C, Server) {
-        let Builder { service: server, interface: Interface { port, listener, mut messages_rx }, .. } = self;
+        let Builder { service: server, server_options, client_options, interface: Interface { port, listener, mut messages_rx, .. }, .. } = self;
         let client_callback_map: Rc<RefCell<client::CallbackMap<C::Response>>> = Default::default();
         let (server_requests_tx, server_requests_rx) = mpsc::unbounded();
         let (abort_requests_tx, abort_requests_rx) = mpsc::unbounded();
+        let (shutdown_tx, shutdown_rx) = mpsc::unbounded();
         let client_callback_map_cloned = client_callback_map.clone();
+        let shutdown: Rc<Cell<bool>> = Default::default();
+        let shutdown_cloned = shutdown.clone();
+        let client_stats: Rc<RefCell<client::Stats>> = Default::default();
+        let client_stats_cloned = client_stats.clone();
+        let server_stats: Rc<RefCell<server::Stats>> = Default::default();
+        let server_stats_cloned = server_stats.clone();
+        let port_cloned = port.clone();
         let dispatcher = async move {
             while let Some(array) = messages_rx.next().await {
                 let message = array.shift().dyn_into::<ArrayBuffer>().unwrap();
-                let message = Uint8Array::new(&message).to_vec();
-                match bincode::deserialize::<Message<S::Request, C::Response>>(&message).unwrap() {
-                    Message::Response(seq_id, response) => {
-                        if let Some(callback_tx) = client_callback_map_cloned.borrow_mut().remove(&seq_id) {
-                            let _ = callback_tx.send((response, array));
+                match codec::decode::<Message<S::Request, C::Response>>(&message, &array).unwrap() {
+                    Message::Response(seq_id, metadata, response) => {
+                        client_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        let callback = client_callback_map_cloned.borrow_mut().remove(&seq_id);
+                        if let Some(callback) = callback {
+                            callback(Ok((response, metadata, array)));
                         }
                     },
-                    Message::Request(seq_id, request) =>
-                        server_requests_tx.unbounded_send((seq_id, request, array)).unwrap(),
-                    Message::Abort(seq_id) =>
-                        abort_requests_tx.unbounded_send(seq_id).unwrap(),
+                    Message::Error(seq_id, error) => {
+                        client_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        let callback = client_callback_map_cloned.borrow_mut().remove(&seq_id);
+                        if let Some(callback) = callback {
+                            callback(Err(error));
+                        }
+                    },
+                    Message::Shutdown => {
+                        client_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        shutdown_cloned.set(true);
+                        let callbacks = std::mem::take(&mut *client_callback_map_cloned.borrow_mut());
+                        for (_, callback) in callbacks {
+                            callback(Err(Error::Shutdown));
+                        }
+                    },
+                    Message::Request(seq_id, metadata, timeout, request) => {
+                        server_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        let context = RequestContext::new(seq_id, metadata, timeout);
+                        /* the requests that arrive after the server has shut down are rejected */
+                        if server_requests_tx.unbounded_send((context, request, array)).is_err() {
+                            let bytes_sent = Message::<C::Request, S::Response>::Error(seq_id, Error::Shutdown)
+                                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+                            let mut server_stats = server_stats_cloned.borrow_mut();
+                            server_stats.bytes_sent += bytes_sent as u64;
+                            server_stats.errors += 1;
+                        }
+                    },
+                    /* the aborts that arrive after the server has shut down are ignored */
+                    Message::Abort(seq_id) => {
+                        server_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        let _ = abort_requests_tx.unbounded_send((seq_id, false));
+                    },
+                    Message::Cancel(seq_id) => {
+                        server_stats_cloned.borrow_mut().bytes_received += message.byte_length() as u64;
+                        let _ = abort_requests_tx.unbounded_send((seq_id, true));
+                    },
                 }
             }
+            /* the other side has closed the virtual channel */
+            shutdown_cloned.set(true);
+            let callbacks = std::mem::take(&mut *client_callback_map_cloned.borrow_mut());
+            for (_, callback) in callbacks {
+                callback(Err(Error::Shutdown));
+            }
         }.boxed_local().shared();
         let port_cloned = port.clone();
+        let client_callback_map_cloned = client_callback_map.clone();
+        let client_stats_cloned = client_stats.clone();
         let abort_sender = move |seq_id: usize| {
-            let abort = Message::<C::Request, S::Response>::Abort(seq_id);
-            let abort = bincode::serialize(&abort).unwrap();
-            let buffer = js_sys::Uint8Array::from(&abort[..]).buffer();
-            let post_args = js_sys::Array::of1(&buffer);
-            let transfer_args = js_sys::Array::of1(&buffer);
-            port_cloned.post_message(&post_args, &transfer_args).unwrap();
+            client_callback_map_cloned.borrow_mut().remove(&seq_id);
+            let bytes_sent = Message::<C::Request, S::Response>::Abort(seq_id)
+                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+            client_stats_cloned.borrow_mut().bytes_sent += bytes_sent as u64;
+        };
+        let port_cloned = port.clone();
+        let client_stats_cloned = client_stats.clone();
+        let cancel_sender = move |seq_id: usize| {
+            let bytes_sent = Message::<C::Request, S::Response>::Cancel(seq_id)
+                .post(&port_cloned, js_sys::Array::new(), js_sys::Array::new());
+            client_stats_cloned.borrow_mut().bytes_sent += bytes_sent as u64;
+        };
+        let request_serializer = |seq_id: usize, metadata: Metadata, timeout: Option<Duration>, request: C::Request| {
+            let request = Message::<C::Request, S::Response>::Request(seq_id, metadata, timeout, request);
+            codec::encode(&request)
         };
-        let request_serializer = |seq_id: usize, request: C::Request| {
-            let request = Message::<C::Request, S::Response>::Request(seq_id, request);
-            bincode::serialize(&request).unwrap()
+        let register_callback = move |seq_id: usize, callback: client::ResponseCallback<C::Response>| {
+            client_callback_map.borrow_mut().insert(seq_id, callback);
         };
-        let listener = Rc::new(listener);
-        let client = C::from((
-            client_callback_map,
+        let client = C::from(client::Configuration::new(
             port.clone(),
             listener.clone(),
             dispatcher.clone(),
             Rc::new(request_serializer),
+            Rc::new(register_callback),
             Rc::new(abort_sender),
+            Rc::new(cancel_sender),
+            shutdown,
+            client_stats,
+            client_options,
         ));
         let server = Server {
             _listener: listener,
@@ -428,8 +1135,13 @@ impl<C, S> Builder<C, S> where
                 port,
                 dispatcher,
                 server_requests_rx,
-                abort_requests_rx
-            ).boxed_local()
+                abort_requests_rx,
+                shutdown_rx,
+                server_stats.clone(),
+                server_options
+            ).boxed_local(),
+            shutdown_tx,
+            stats: server_stats,
         };
         (client, server)
     }
@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+
+use js_sys::Array;
+use wasm_bindgen::{JsCast, JsValue};
+
+thread_local! {
+    static POSTED: RefCell<Option<Posted>> = const { RefCell::new(None) };
+}
+
+/// The values that are posted alongside a message by the types that derive
+/// [`macro@crate::Postable`]
+enum Posted {
+    /* the size of the message is being measured and the posted values are ignored */
+    Measuring,
+    /* the message is being serialized and the posted values are collected */
+    Collecting { post: Array, transfer: Array },
+    /* the message is being deserialized and the posted values are taken from the front */
+    Attaching(Array),
+}
+
+/// Run `measure` while the types that derive [`macro@crate::Postable`] ignore their posted
+/// values, so that the size of a message can be measured before it is serialized
+pub(crate) fn measure<T>(measure: impl FnOnce() -> T) -> T {
+    let previous = POSTED.with(|posted| posted.replace(Some(Posted::Measuring)));
+    let result = measure();
+    POSTED.with(|posted| posted.replace(previous));
+    result
+}
+
+/// Run `serialize` while collecting the values that are posted by the types that derive
+/// [`macro@crate::Postable`], returning the result of `serialize` together with the posted
+/// and transferred values
+pub(crate) fn collect<T>(serialize: impl FnOnce() -> T) -> (T, Array, Array) {
+    let (post, transfer) = (Array::new(), Array::new());
+    let previous = POSTED.with(|posted| posted.replace(Some(Posted::Collecting {
+        post: post.clone(),
+        transfer: transfer.clone(),
+    })));
+    let result = serialize();
+    POSTED.with(|posted| posted.replace(previous));
+    (result, post, transfer)
+}
+
+/// Run `deserialize` while the types that derive [`macro@crate::Postable`] take their posted
+/// values from the front of `post`
+pub(crate) fn attach<T>(post: &Array, deserialize: impl FnOnce() -> T) -> T {
+    let previous = POSTED.with(|posted| posted.replace(Some(Posted::Attaching(post.clone()))));
+    let result = deserialize();
+    POSTED.with(|posted| posted.replace(previous));
+    result
+}
+
+/// Post `value` alongside the message that is being serialized, transferring it if `transfer`
+/// is set
+pub fn post(value: &JsValue, transfer: bool) -> Result<(), &'static str> {
+    post_with(transfer, || value.clone())
+}
+
+/// Post the value returned by `value` alongside the message that is being serialized, `value`
+/// is not called while the size of the message is being measured
+pub(crate) fn post_with(transfer: bool, value: impl FnOnce() -> JsValue) -> Result<(), &'static str> {
+    POSTED.with(|posted| match &*posted.borrow() {
+        Some(Posted::Measuring) => Ok(()),
+        Some(Posted::Collecting { post, transfer: transferred }) => {
+            let value = value();
+            post.push(&value);
+            if transfer {
+                transferred.push(&value);
+            }
+            Ok(())
+        },
+        _ => Err("posted values can only be serialized while a message is being sent"),
+    })
+}
+
+/// Take the next posted value of the message that is being deserialized
+pub fn take<T: JsCast>() -> Result<T, &'static str> {
+    POSTED.with(|posted| match &*posted.borrow() {
+        Some(Posted::Attaching(post)) if post.length() > 0 => post.shift()
+            .dyn_into::<T>()
+            .map_err(|_| "the posted value has the wrong type"),
+        Some(Posted::Attaching(_)) => Err("the message is missing a posted value"),
+        _ => Err("posted values can only be deserialized while a message is being received"),
+    })
+}
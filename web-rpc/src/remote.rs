@@ -0,0 +1,157 @@
+use std::{rc::Rc, time::Duration};
+
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{client, service, Builder, Interface};
+
+/// The tag of the message that the client of a remote object or the receiver of a callback
+/// posts once its last clone has been dropped
+const RELEASE: &str = "web-rpc:release";
+
+/// Create the release message, this is an array so that the interface that listens on the same
+/// port as a remote object's server does not mistake it for a handshake
+fn release_message() -> JsValue {
+    js_sys::Array::of1(&JsValue::from_str(RELEASE)).into()
+}
+
+/// Check whether `message` is the release message of a remote object or a callback
+pub(crate) fn is_release(message: &JsValue) -> bool {
+    message.dyn_ref::<js_sys::Array>()
+        .is_some_and(|array| array.length() == 1 && array.get(0).as_string().is_some_and(|tag| tag == RELEASE))
+}
+
+/// Post the release message to `port`
+pub(crate) fn release(port: &web_sys::MessagePort) {
+    let _ = port.post_message(&release_message());
+}
+
+/// `Remote` is a handle to an object that is served on its own channel. Methods can return or
+/// accept remotes, in which case the channel is transferred alongside the request or the
+/// response. The type parameter is the client of the object's service, e.g., a method
+/// returning `Remote<FileClient>` allows the caller to use the returned object via the methods
+/// of `FileClient`.
+///
+/// A remote is created with [`Remote::new`], which spawns a server for the object. The remote
+/// that is received on the other side of the channel provides the client of the object via
+/// [`Remote::client`] or [`Remote::into_client`]. The arguments and return values that are
+/// remotes must be listed in the `remote` attribute of their method, e.g., `#[remote(return)]`.
+/// The object's server is shut down once the received remote and all clones of its client have
+/// been dropped or if the remote is dropped without being sent.
+pub struct Remote<C> {
+    /* the port of the channel before the remote is sent */
+    port: Option<web_sys::MessagePort>,
+    /* the client of the object once the remote has been received */
+    client: Option<C>,
+}
+
+impl<C: client::Client> Remote<C> {
+    /// Serve `implementation` using the service `S` on a new channel. The service must use
+    /// the same request and response types as the client `C`, i.e., `S` and `C` are usually
+    /// the service and the client that were generated for the same trait.
+    pub fn new<S>(implementation: impl Into<S>) -> Self where
+        S: service::Service<Request = C::Request, Response = C::Response> + 'static,
+        <S as service::Service>::Request: DeserializeOwned,
+        <S as service::Service>::Response: Serialize {
+        let channel = web_sys::MessageChannel::new().unwrap();
+        let server_port = channel.port1();
+        let server = Builder::new(Interface::entangled(server_port.clone()))
+            .with_service::<S>(implementation)
+            .build();
+        let server_handle = server.handle();
+        let listener = gloo_events::EventListener::new(&server_port, "message", move |event| {
+            let message = event.unchecked_ref::<web_sys::MessageEvent>().data();
+            if is_release(&message) {
+                let server_handle = server_handle.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    server_handle.shutdown(Duration::ZERO).await
+                });
+            }
+        });
+        /* the server completes once it has been shut down, dropping the object */
+        wasm_bindgen_futures::spawn_local(async move {
+            let _listener = listener;
+            server.await
+        });
+        Self {
+            port: Some(channel.port2()),
+            client: None,
+        }
+    }
+
+    /// Get the client of the object, this returns `None` if the remote was created using
+    /// [`Remote::new`] instead of being received
+    pub fn client(&self) -> Option<&C> {
+        self.client.as_ref()
+    }
+
+    /// Convert the remote into the client of the object, the object is kept alive by the client
+    /// and its clones. This method panics if the remote has not been received.
+    pub fn into_client(mut self) -> C {
+        self.client.take()
+            .expect("only remotes that have been received have a client")
+    }
+
+    #[doc(hidden)]
+    pub fn into_port(mut self) -> web_sys::MessagePort {
+        self.port.take()
+            .expect("only remotes that have been created using `Remote::new` can be sent")
+    }
+}
+
+impl<C> Remote<C> where
+    C: client::Client + From<client::Configuration<C::Request, C::Response>> + 'static,
+    <C as client::Client>::Response: DeserializeOwned,
+    <C as client::Client>::Request: Serialize {
+    #[doc(hidden)]
+    pub fn from_port(port: JsValue) -> Self {
+        let port = port.dyn_into::<web_sys::MessagePort>().unwrap();
+        let mut builder = Builder::new(Interface::entangled(port.clone()))
+            .with_client::<C>();
+        builder.client_options.release = Some(Rc::new(Release::new(port)));
+        Self {
+            port: None,
+            client: Some(builder.build()),
+        }
+    }
+}
+
+impl<C> std::fmt::Debug for Remote<C> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.debug_struct("Remote")
+            .field("received", &self.client.is_some())
+            .finish()
+    }
+}
+
+impl<C> Drop for Remote<C> {
+    fn drop(&mut self) {
+        /* the remote was never sent, hence the object cannot be used */
+        if let Some(port) = self.port.take() {
+            release(&port);
+        }
+    }
+}
+
+/// Shuts down the server of a remote object or releases a callback when dropped, this is
+/// shared by the clones of the object's client or the callback
+#[derive(Debug)]
+pub(crate) struct Release {
+    port: web_sys::MessagePort,
+}
+
+impl Release {
+    pub(crate) fn new(port: web_sys::MessagePort) -> Self {
+        Self { port }
+    }
+
+    pub(crate) fn port(&self) -> &web_sys::MessagePort {
+        &self.port
+    }
+}
+
+impl Drop for Release {
+    fn drop(&mut self) {
+        release(&self.port);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, collections::HashMap, pin::Pin, rc::Rc, task::{self, Poll}, time::Duration};
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::{future::LocalBoxFuture, Future};
+use futures_util::FutureExt;
+use gloo_events::EventListener;
+
+use crate::Overflow;
+
+/// `Server` is the server that is returned from the [`crate::Builder::build`] method given
+/// you configured the RPC interface with a service. Note that `Server` implements future and needs
+/// to be polled in order to execute and respond to inbound RPC requests. The future completes
+/// once the server has been shut down via a [`ServerHandle`] or once the other side of its
+/// virtual channel has been closed.
+#[must_use = "Server must be polled in order for RPC requests to be executed"]
+pub struct Server {
+    pub(crate) _listener: Rc<EventListener>,
+    pub(crate) task: LocalBoxFuture<'static, ()>,
+    pub(crate) shutdown_tx: mpsc::UnboundedSender<ShutdownRequest>,
+    pub(crate) stats: Rc<RefCell<Stats>>,
+}
+
+impl Server {
+    /// Get a handle for shutting down this server. Since the server is usually spawned, the
+    /// handle should be obtained before doing so.
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            shutdown_tx: self.shutdown_tx.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Get the current statistics of this server
+    pub fn stats(&self) -> Stats {
+        self.stats.borrow().clone()
+    }
+}
+
+impl Future for Server {
+    type Output = ();
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>
+    ) -> Poll<Self::Output> {
+        self.task.poll_unpin(cx)
+    }
+}
+
+/// The timeout of a shutdown and the sender that is notified once the shutdown has completed
+pub(crate) type ShutdownRequest = (Duration, oneshot::Sender<()>);
+
+/// A handle for shutting down a [`Server`], see [`Server::handle`]
+#[derive(Clone)]
+pub struct ServerHandle {
+    shutdown_tx: mpsc::UnboundedSender<ShutdownRequest>,
+    stats: Rc<RefCell<Stats>>,
+}
+
+impl ServerHandle {
+    /// Shut down the server. The server stops accepting requests and rejects the requests that
+    /// have not been started with [`crate::Error::Shutdown`]. The requests that are running are
+    /// given until `timeout` to complete after which they are aborted. Finally, the server tells
+    /// the client on the other side of the channel that it has shut down so that the client's
+    /// pending and future calls fail with [`crate::Error::Shutdown`]. The returned future
+    /// resolves once the shutdown has completed or immediately if the server is gone.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.shutdown_tx.unbounded_send((timeout, done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    /// Get the current statistics of the server
+    pub fn stats(&self) -> Stats {
+        self.stats.borrow().clone()
+    }
+}
+
+/// The statistics of a server, see [`Server::stats`]. The byte counts only include the
+/// serialized part of the messages and not the posted Javascript values.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of requests that are being executed
+    pub in_flight: usize,
+    /// The number of requests that are waiting to be executed
+    pub queued: usize,
+    /// The total number of requests that have been received per method
+    pub requests: HashMap<&'static str, u64>,
+    /// The total number of requests that were aborted or cancelled by the client
+    pub aborts: u64,
+    /// The total number of requests that were rejected with a [`crate::Error`]
+    pub errors: u64,
+    /// The total number of bytes that have been sent
+    pub bytes_sent: u64,
+    /// The total number of bytes that have been received
+    pub bytes_received: u64,
+}
+
+/// The policy that a server uses for executing requests. Note that services that were generated
+/// with the `actor` argument always execute their requests sequentially.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Execution {
+    /// Requests are executed concurrently. This is the default policy.
+    #[default]
+    Concurrent,
+    /// Requests are executed one at a time in the order in which they were received.
+    Sequential,
+    /// Requests with the same order key are executed one at a time in the order in which they
+    /// were received. The order key is the argument of a method that has been annotated with
+    /// `#[order_key]`. Requests with different order keys and requests to methods without an
+    /// order key are executed concurrently.
+    Keyed,
+}
+
+/// A layer wraps the execution of the requests of a server, e.g., for logging, authorization,
+/// or metrics. Layers are added using [`crate::Builder::with_layer`] and receive the decoded
+/// request together with the [`Next`] layer, which they can run to get the response or skip to
+/// fail the request. If the client aborts the request, the future returned by the layer is
+/// dropped unless the method is cooperative, see [`crate::CancellationToken`].
+/// ```rust
+/// struct Logger;
+/// impl<Request: 'static, Response: 'static> Layer<Request, Response> for Logger {
+///     fn call<'a>(
+///         &'a self,
+///         context: Context,
+///         request: Request,
+///         next: Next<'a, Request, Response>
+///     ) -> LocalBoxFuture<'a, Result<Response, web_rpc::Error>> {
+///         Box::pin(async move {
+///             log(format!("executing {} ({})", context.method, context.seq_id));
+///             next.run(request).await
+///         })
+///     }
+/// }
+/// ```
+pub trait Layer<Request, Response> {
+    fn call<'a>(
+        &'a self,
+        context: Context,
+        request: Request,
+        next: Next<'a, Request, Response>
+    ) -> LocalBoxFuture<'a, Result<Response, crate::Error>>;
+}
+
+/// Information about the request that a [`Layer`] is called for
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Context {
+    /// The name of the method that the request is for
+    pub method: &'static str,
+    /// The sequence id of the request, this is unique among the requests from a client
+    pub seq_id: usize,
+}
+
+/// The rest of the layers and the service that a [`Layer`] wraps
+pub struct Next<'a, Request, Response> {
+    pub(crate) run: Box<dyn FnOnce(Request) -> LocalBoxFuture<'a, Result<Response, crate::Error>> + 'a>,
+}
+
+impl<Request, Response> Next<'_, Request, Response> {
+    /// Run the rest of the layers and the service for `request`. Note that values that
+    /// are posted or transferred in the response are only sent to the client if the response
+    /// returned by this method is the one that the layer returns.
+    pub async fn run(self, request: Request) -> Result<Response, crate::Error> {
+        (self.run)(request).await
+    }
+}
+
+/// The options of a server, these are set via the methods of [`crate::Builder`]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Options {
+    pub(crate) execution: Execution,
+    pub(crate) max_in_flight: Option<usize>,
+    pub(crate) method_limits: HashMap<&'static str, usize>,
+    pub(crate) queue_capacity: Option<usize>,
+    pub(crate) overflow: Overflow,
+}
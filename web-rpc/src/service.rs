@@ -1,66 +1,446 @@
-use std::collections::HashMap;
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet, VecDeque}, hash::{DefaultHasher, Hash, Hasher}, rc::Rc, time::Duration};
 
 use futures_channel::{mpsc, oneshot};
 use futures_core::{future::LocalBoxFuture, Future};
-use futures_util::{future::Shared, stream::FuturesUnordered, StreamExt};
-use js_sys::{Array, Uint8Array};
+use futures_util::{future::Shared, stream::FuturesUnordered, FutureExt, StreamExt};
+use js_sys::Array;
 use serde::Serialize;
 
+use crate::context::scope;
+use crate::server::{Context, Execution, Layer, Next, Options, ShutdownRequest, Stats};
+use crate::Overflow;
+
+/// The result of executing a request, this is `None` if the request was aborted and otherwise
+/// contains the response together with its posted and transferred values
+pub type ExecuteResult<Response> = Option<Result<(Response, Array, Array), crate::Error>>;
+
 pub trait Service {
     type Request;
     type Response;
 
+    /// Services that require exclusive access to their implementation, i.e., services that were
+    /// generated with the `actor` argument, must only execute one request at a time
+    const EXCLUSIVE: bool = false;
+
+    /// The name of the method that a request is for
+    fn method(request: &Self::Request) -> &'static str;
+
+    /// The names of all methods of the service, including the methods of its supertraits
+    fn methods() -> Vec<&'static str>;
+
+    /// The order key of a request, i.e., the hash of the argument annotated with `#[order_key]`
+    fn order_key(_request: &Self::Request) -> Option<u64> {
+        None
+    }
+
+    /// Whether the method of a request is cooperative, i.e., whether it observes the cancellation
+    /// of its request via a [`crate::CancellationToken`] instead of being dropped
+    fn cooperative(_request: &Self::Request) -> bool {
+        false
+    }
+
     fn execute(
         &self,
         seq_id: usize,
         abort_rx: oneshot::Receiver<()>,
         request: Self::Request,
         js_args: Array
-    ) -> impl Future<Output = (usize, Option<(Self::Response, Array, Array)>)>;
+    ) -> impl Future<Output = (usize, ExecuteResult<Self::Response>)>;
+}
+
+/// A service that is wrapped in a [`Layer`], see [`crate::Builder::with_layer`]
+pub struct Layered<S, L> {
+    pub(crate) service: S,
+    pub(crate) layer: L,
+}
+
+impl<S, L> Service for Layered<S, L> where
+    S: Service,
+    L: Layer<S::Request, S::Response> {
+    type Request = S::Request;
+    type Response = S::Response;
+
+    const EXCLUSIVE: bool = S::EXCLUSIVE;
+
+    fn method(request: &Self::Request) -> &'static str {
+        S::method(request)
+    }
+
+    fn methods() -> Vec<&'static str> {
+        S::methods()
+    }
+
+    fn order_key(request: &Self::Request) -> Option<u64> {
+        S::order_key(request)
+    }
+
+    fn cooperative(request: &Self::Request) -> bool {
+        S::cooperative(request)
+    }
+
+    async fn execute(
+        &self,
+        seq_id: usize,
+        abort_rx: oneshot::Receiver<()>,
+        request: Self::Request,
+        js_args: Array
+    ) -> (usize, ExecuteResult<Self::Response>) {
+        let context = Context { method: S::method(&request), seq_id };
+        /* cooperative methods observe the cancellation via their token and must not be dropped */
+        let (_abort_tx, detached_abort_rx) = oneshot::channel();
+        let mut abort_rx = match S::cooperative(&request) {
+            true => detached_abort_rx,
+            false => abort_rx,
+        };
+        /* the posted and transferred values of the response bypass the layer */
+        let js_response: Cell<Option<(Array, Array)>> = Default::default();
+        let next = Next {
+            run: Box::new(|request| async {
+                /* aborts are handled below by dropping the layer's future */
+                let (_abort_tx, abort_rx) = oneshot::channel();
+                match self.service.execute(seq_id, abort_rx, request, js_args).await.1 {
+                    Some(Ok((response, post_args, transfer_args))) => {
+                        js_response.set(Some((post_args, transfer_args)));
+                        Ok(response)
+                    },
+                    Some(Err(error)) => Err(error),
+                    None => unreachable!("request was aborted without being dropped"),
+                }
+            }.boxed_local()),
+        };
+        let response = self.layer.call(context, request, next).fuse();
+        futures_util::pin_mut!(response);
+        futures_util::select! {
+            _ = abort_rx => (seq_id, None),
+            response = response => (seq_id, Some(response.map(|response| {
+                let (post_args, transfer_args) = js_response.take().unwrap_or_default();
+                (response, post_args, transfer_args)
+            }))),
+        }
+    }
+}
+
+/// A request that the server has accepted and that has not completed yet
+struct Accepted {
+    abort_tx: Option<oneshot::Sender<()>>,
+    context: Rc<crate::RequestContext>,
+}
+
+impl Accepted {
+    /// Cancel the request's token and drop the method unless it is cooperative
+    fn abort(&mut self) {
+        self.context.cancel();
+        if let Some(abort_tx) = self.abort_tx.take() {
+            let _ = abort_tx.send(());
+        }
+    }
+}
+
+/// The sequence id of a request that the client aborted and whether the client waits for the
+/// partial result of the request, i.e., whether the request was cancelled rather than dropped
+pub(crate) type AbortRequest = (usize, bool);
+
+pub fn hash_order_key<K: Hash + ?Sized>(order_key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    order_key.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct Queued<Request> {
+    seq_id: usize,
+    context: Rc<crate::RequestContext>,
+    method: &'static str,
+    order_key: Option<u64>,
+    request: Request,
+    js_args: Array,
+    abort_rx: oneshot::Receiver<()>,
 }
 
+/// The scheduler queues requests and decides which requests can be started according
+/// to the execution policy and the limits of the server
+struct Scheduler<Request> {
+    execution: Execution,
+    max_in_flight: Option<usize>,
+    method_limits: HashMap<&'static str, usize>,
+    queue: VecDeque<Queued<Request>>,
+    /* the methods and order keys of the running requests */
+    running: HashMap<usize, (&'static str, Option<u64>)>,
+}
+
+impl<Request> Scheduler<Request> {
+    fn new(execution: Execution, options: &Options) -> Self {
+        Self {
+            execution,
+            max_in_flight: options.max_in_flight,
+            method_limits: options.method_limits.clone(),
+            queue: Default::default(),
+            running: Default::default(),
+        }
+    }
+
+    fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn running(&self) -> usize {
+        self.running.len()
+    }
+
+    fn push(&mut self, queued: Queued<Request>) {
+        self.queue.push_back(queued);
+    }
+
+    /// Remove a request that has not been started yet, returning whether it was queued
+    fn remove(&mut self, seq_id: usize) -> bool {
+        let index = self.queue.iter().position(|queued| queued.seq_id == seq_id);
+        index.and_then(|index| self.queue.remove(index)).is_some()
+    }
+
+    /// Remove the request that was queued last
+    fn pop_back(&mut self) -> Option<Queued<Request>> {
+        self.queue.pop_back()
+    }
+
+    /// Check whether the limit for `method` allows another request to be started
+    fn below_method_limit(&self, method: &'static str) -> bool {
+        self.method_limits.get(method).is_none_or(|&limit| {
+            self.running.values().filter(|(running_method, _)| *running_method == method).count() < limit
+        })
+    }
+
+    /// Take the next request that can be started and mark it as running
+    fn next(&mut self) -> Option<Queued<Request>> {
+        if self.max_in_flight.is_some_and(|max_in_flight| self.running.len() >= max_in_flight) {
+            return None;
+        }
+        let index = match self.execution {
+            Execution::Concurrent => self.queue.iter()
+                .position(|queued| self.below_method_limit(queued.method)),
+            Execution::Sequential => self.queue.front()
+                .filter(|queued| self.running.is_empty() && self.below_method_limit(queued.method))
+                .map(|_| 0),
+            Execution::Keyed => {
+                let mut running_keys = self.running.values()
+                    .filter_map(|(_, order_key)| *order_key)
+                    .collect::<HashSet<_>>();
+                /* a request can only be started if no request with the same order key is running
+                   and no request with the same order key is queued ahead of it */
+                self.queue.iter().position(|queued| {
+                    let order_key_available = match queued.order_key {
+                        Some(order_key) => running_keys.insert(order_key),
+                        None => true,
+                    };
+                    order_key_available && self.below_method_limit(queued.method)
+                })
+            }
+        }?;
+        let queued = self.queue.remove(index)?;
+        self.running.insert(queued.seq_id, (queued.method, queued.order_key));
+        Some(queued)
+    }
+
+    fn finish(&mut self, seq_id: usize) {
+        self.running.remove(&seq_id);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn task<S, Request>(
     service: S,
-    port: crate::port::Port,
+    port: crate::interface::Sender,
     mut dispatcher: Shared<LocalBoxFuture<'static, ()>>,
-    mut server_requests_rx: mpsc::UnboundedReceiver<(usize, <S as Service>::Request, js_sys::Array)>,
-    mut abort_requests_rx: mpsc::UnboundedReceiver<usize>,
+    mut server_requests_rx: mpsc::UnboundedReceiver<(crate::RequestContext, <S as Service>::Request, js_sys::Array)>,
+    mut abort_requests_rx: mpsc::UnboundedReceiver<AbortRequest>,
+    mut shutdown_rx: mpsc::UnboundedReceiver<ShutdownRequest>,
+    stats: Rc<RefCell<Stats>>,
+    options: Options,
 ) where
     S: Service + 'static,
     Request: Serialize,
     <S as Service>::Response: Serialize {
-    let mut server_tasks: HashMap<usize, oneshot::Sender<_>> = Default::default();
+    let mut server_tasks: HashMap<usize, Accepted> = Default::default();
     let mut server_responses_rx: FuturesUnordered<_> = Default::default();
-    loop {
+    /* the requests that wait for space in the queue */
+    let mut waiting: VecDeque<(crate::RequestContext, S::Request, Array)> = Default::default();
+    let mut scheduler = Scheduler::new(match S::EXCLUSIVE {
+        true => Execution::Sequential,
+        false => options.execution,
+    }, &options);
+    let post_message = |message: crate::Message<Request, S::Response>, post_args, transfer_args| {
+        stats.borrow_mut().bytes_sent += message.post(&port, post_args, transfer_args) as u64;
+    };
+    let post_result = |seq_id, result: Result<(S::Response, Array, Array), crate::Error>, metadata| match result {
+        Ok((response, post_args, transfer_args)) =>
+            post_message(crate::Message::Response(seq_id, metadata, response), post_args, transfer_args),
+        Err(error) => {
+            stats.borrow_mut().errors += 1;
+            post_message(crate::Message::Error(seq_id, error), Array::new(), Array::new());
+        }
+    };
+    let accept = |
+        server_tasks: &mut HashMap<usize, Accepted>,
+        scheduler: &mut Scheduler<_>,
+        (context, request, js_args): (crate::RequestContext, S::Request, Array)
+    | {
+        let context = Rc::new(context);
+        let seq_id = context.seq_id();
+        let (abort_tx, abort_rx) = oneshot::channel::<()>();
+        server_tasks.insert(seq_id, Accepted { abort_tx: Some(abort_tx), context: context.clone() });
+        let method = S::method(&request);
+        let order_key = S::order_key(&request);
+        scheduler.push(Queued { seq_id, context, method, order_key, request, js_args, abort_rx });
+    };
+    let abort = |
+        server_tasks: &mut HashMap<usize, Accepted>,
+        scheduler: &mut Scheduler<_>,
+        waiting: &mut VecDeque<(crate::RequestContext, S::Request, Array)>,
+        (seq_id, reply)
+    | {
+        if let Some(index) = waiting.iter().position(|(context, ..)| context.seq_id() == seq_id) {
+            waiting.remove(index);
+            stats.borrow_mut().aborts += 1;
+            if reply {
+                post_result(seq_id, Err(crate::Error::Cancelled), Default::default());
+            }
+            return;
+        }
+        let started = !scheduler.remove(seq_id);
+        if reply && started {
+            /* the partial result or the cancellation of the method is sent once it completes */
+            if let Some(accepted) = server_tasks.get_mut(&seq_id) {
+                stats.borrow_mut().aborts += 1;
+                accepted.abort();
+            }
+        } else if let Some(mut accepted) = server_tasks.remove(&seq_id) {
+            stats.borrow_mut().aborts += 1;
+            accepted.abort();
+            if reply {
+                post_result(seq_id, Err(crate::Error::Cancelled), Default::default());
+            }
+        }
+    };
+    let (timeout, done_tx) = loop {
+        loop {
+            while let Some(Queued { seq_id, context, request, js_args, abort_rx, .. }) = scheduler.next() {
+                /* the context is available to the service via `RequestContext::current` while it executes */
+                let response = scope(context.clone(), service.execute(seq_id, abort_rx, request, js_args));
+                server_responses_rx.push(response.map(move |(seq_id, response)|
+                    (seq_id, response, context.take_response_metadata())));
+            }
+            /* while the queue is full, the requests wait outside of the queue until a queued request
+               has been started, the messages from the interface are still received in the meantime */
+            let has_space = options.overflow == Overflow::Reject || options.queue_capacity
+                .is_none_or(|queue_capacity| scheduler.queued() < queue_capacity);
+            match waiting.pop_front() {
+                Some(server_request) if has_space =>
+                    accept(&mut server_tasks, &mut scheduler, server_request),
+                Some(server_request) => {
+                    waiting.push_front(server_request);
+                    break;
+                }
+                None => break,
+            }
+        }
+        /* reject the requests that do not fit in the queue */
+        while options.queue_capacity.is_some_and(|queue_capacity| scheduler.queued() > queue_capacity) {
+            let Queued { seq_id, .. } = scheduler.pop_back().unwrap();
+            server_tasks.remove(&seq_id);
+            stats.borrow_mut().errors += 1;
+            post_message(crate::Message::Error(seq_id, crate::Error::Overloaded), Array::new(), Array::new());
+        }
+        stats.borrow_mut().in_flight = scheduler.running();
+        stats.borrow_mut().queued = scheduler.queued() + waiting.len();
         futures_util::select! {
             _ = dispatcher => {}
             server_request = server_requests_rx.next() => {
-                let (seq_id, request, post_args) = server_request.unwrap();
-                let (abort_tx, abort_rx) = oneshot::channel::<()>();
-                server_tasks.insert(seq_id, abort_tx);
-                server_responses_rx.push(service.execute(seq_id, abort_rx, request, post_args));
+                match server_request {
+                    Some(server_request) => {
+                        *stats.borrow_mut().requests.entry(S::method(&server_request.1)).or_default() += 1;
+                        waiting.push_back(server_request);
+                    }
+                    /* the other side has closed the virtual channel, so nobody awaits the responses */
+                    None => break (Duration::ZERO, None),
+                }
             },
             abort_request = abort_requests_rx.next() => {
-                if let Some(seq_id) = abort_request {
-                    if let Some(abort_tx) = server_tasks.remove(&seq_id) {
-                        let _ = abort_tx.send(());
+                if let Some(abort_request) = abort_request {
+                    abort(&mut server_tasks, &mut scheduler, &mut waiting, abort_request);
+                }
+            },
+            server_response = server_responses_rx.next() => {
+                if let Some((seq_id, response, metadata)) = server_response {
+                    scheduler.finish(seq_id);
+                    if server_tasks.remove(&seq_id).is_some() {
+                        /* the method was dropped after its request was cancelled */
+                        let result = response.unwrap_or(Err(crate::Error::Cancelled));
+                        post_result(seq_id, result, metadata);
                     }
                 }
             },
+            shutdown_request = shutdown_rx.next() => {
+                if let Some((timeout, done_tx)) = shutdown_request {
+                    break (timeout, Some(done_tx));
+                }
+            }
+        }
+    };
+    /* shut down, starting by rejecting the requests that have not been started */
+    let mut done_txs = Vec::from_iter(done_tx);
+    while let Some((context, ..)) = waiting.pop_back() {
+        stats.borrow_mut().errors += 1;
+        post_message(crate::Message::Error(context.seq_id(), crate::Error::Shutdown), Array::new(), Array::new());
+    }
+    while let Some(Queued { seq_id, .. }) = scheduler.pop_back() {
+        server_tasks.remove(&seq_id);
+        stats.borrow_mut().errors += 1;
+        post_message(crate::Message::Error(seq_id, crate::Error::Shutdown), Array::new(), Array::new());
+    }
+    stats.borrow_mut().queued = 0;
+    /* give the running requests until the timeout to complete */
+    let mut timeout = gloo_timers::future::sleep(timeout).fuse();
+    while !server_responses_rx.is_empty() {
+        stats.borrow_mut().in_flight = server_responses_rx.len();
+        futures_util::select! {
+            _ = dispatcher => {}
+            server_request = server_requests_rx.next() => {
+                if let Some((context, request, _)) = server_request {
+                    let seq_id = context.seq_id();
+                    *stats.borrow_mut().requests.entry(S::method(&request)).or_default() += 1;
+                    stats.borrow_mut().errors += 1;
+                    post_message(crate::Message::Error(seq_id, crate::Error::Shutdown), Array::new(), Array::new());
+                }
+            },
+            abort_request = abort_requests_rx.next() => {
+                if let Some(abort_request) = abort_request {
+                    abort(&mut server_tasks, &mut scheduler, &mut waiting, abort_request);
+                }
+            },
             server_response = server_responses_rx.next() => {
-                if let Some((seq_id, response)) = server_response {
+                if let Some((seq_id, response, metadata)) = server_response {
                     if server_tasks.remove(&seq_id).is_some() {
-                        if let Some((response, post_args, transfer_args)) = response {
-                            let response = crate::Message::<Request, S::Response>::Response(seq_id, response);
-                            let response = bincode::serialize(&response).unwrap();
-                            let buffer = Uint8Array::from(&response[..]).buffer();
-                            post_args.unshift(&buffer);
-                            transfer_args.unshift(&buffer);
-                            port.post_message(&post_args, &transfer_args).unwrap();
-                        }
+                        let result = response.unwrap_or(Err(crate::Error::Cancelled));
+                        post_result(seq_id, result, metadata);
                     }
                 }
+            },
+            shutdown_request = shutdown_rx.next() => {
+                if let Some((_, done_tx)) = shutdown_request {
+                    done_txs.push(done_tx);
+                }
+            },
+            _ = timeout => {
+                /* abort the requests that are still running */
+                for (_, mut accepted) in server_tasks.drain() {
+                    accepted.abort();
+                }
+                break;
             }
         }
     }
-}
\ No newline at end of file
+    /* tell the client that the server has shut down so that its pending calls fail */
+    stats.borrow_mut().in_flight = 0;
+    post_message(crate::Message::Shutdown, Array::new(), Array::new());
+    for done_tx in done_txs {
+        let _ = done_tx.send(());
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+#[web_rpc::service(actor)]
+pub trait Counter {
+    async fn increment(interval: Duration) -> u32;
+    fn count() -> u32;
+}
+
+#[derive(Default)]
+struct CounterServiceImpl {
+    count: u32,
+}
+impl Counter for CounterServiceImpl {
+    async fn increment(&mut self, interval: Duration) -> u32 {
+        let count = self.count;
+        /* other requests are not executed while this request is sleeping */
+        gloo_timers::future::sleep(interval).await;
+        self.count = count + 1;
+        self.count
+    }
+    fn count(&mut self) -> u32 {
+        self.count
+    }
+}
+
+#[wasm_bindgen_test]
+async fn actor() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let (server, _server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<CounterService<_>>(CounterServiceImpl::default())
+        .with_queue_capacity(2)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<CounterClient>()
+        .build();
+    /* run test */
+    let responses = futures_util::future::join4(
+        client.increment(Duration::from_millis(50)),
+        client.increment(Duration::from_millis(20)),
+        client.increment(Duration::from_millis(10)),
+        client.count(),
+    ).await;
+    assert_eq!(responses, (1, 2, 3, 3));
+}
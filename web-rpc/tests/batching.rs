@@ -0,0 +1,48 @@
+use std::{cell::Cell, rc::Rc};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Calculator {
+    fn add(left: u32, right: u32) -> u32;
+}
+struct CalculatorServiceImpl;
+impl Calculator for CalculatorServiceImpl {
+    fn add(&self, left: u32, right: u32) -> u32 {
+        left + right
+    }
+}
+
+#[wasm_bindgen_test]
+async fn batching() {
+    console_error_panic_hook::set_once();
+    /* create channel and count the messages that arrive at the server */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let received = Rc::new(Cell::new(0));
+    let received_cloned = received.clone();
+    let _counter = gloo_events::EventListener::new(&channel.port1(), "message", move |_| {
+        received_cloned.set(received_cloned.get() + 1);
+    });
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server, the responses are batched per animation frame */
+    let server = web_rpc::Builder::new(server_interface.with_batching(web_rpc::interface::Batching::AnimationFrame))
+        .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client, the requests are batched per microtask */
+    let client = web_rpc::Builder::new(client_interface.with_batching(web_rpc::interface::Batching::Microtask))
+        .with_client::<CalculatorClient>()
+        .build();
+    /* the requests that are sent within the same turn are posted as a single message */
+    let handshake = received.get();
+    let responses = futures_util::future::join_all((0..100).map(|value| client.add(value, value))).await;
+    assert_eq!(responses, (0..100).map(|value| value * 2).collect::<Vec<_>>());
+    assert_eq!(received.get() - handshake, 1);
+    /* requests that are sent in different turns are posted separately */
+    assert_eq!(client.add(1, 2).await, 3);
+    assert_eq!(client.add(3, 4).await, 7);
+    assert_eq!(received.get() - handshake, 3);
+}
@@ -0,0 +1,65 @@
+use wasm_bindgen_test::*;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Upload {
+    name: String,
+    contents: web_rpc::Bytes,
+}
+
+#[web_rpc::service]
+pub trait Storage {
+    fn checksum(contents: web_rpc::Bytes) -> u32;
+    fn generate(len: u32) -> web_rpc::Bytes;
+    fn echo(contents: web_rpc::Bytes) -> web_rpc::Bytes;
+    fn upload(upload: Upload) -> String;
+}
+struct StorageServiceImpl;
+impl Storage for StorageServiceImpl {
+    fn checksum(&self, contents: web_rpc::Bytes) -> u32 {
+        contents.into_vec().into_iter().map(u32::from).sum()
+    }
+    fn generate(&self, len: u32) -> web_rpc::Bytes {
+        (0..len).map(|index| index as u8).collect::<Vec<_>>().into()
+    }
+    fn echo(&self, contents: web_rpc::Bytes) -> web_rpc::Bytes {
+        contents
+    }
+    fn upload(&self, upload: Upload) -> String {
+        format!("{}: {} bytes", upload.name, upload.contents.len())
+    }
+}
+
+#[wasm_bindgen_test]
+async fn bytes() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<StorageService<_>>(StorageServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<StorageClient>()
+        .build();
+    /* bytes are transferred instead of serialized */
+    let contents = vec![1u8; 1024 * 1024];
+    assert_eq!(client.checksum(contents.into()).await, 1024 * 1024);
+    assert!(client.stats().bytes_sent < 1024);
+    let generated = client.generate(256).await;
+    assert_eq!(generated.len(), 256);
+    assert_eq!(generated.to_vec(), (0..=255).collect::<Vec<u8>>());
+    /* bytes that are in Javascript memory are transferred without being copied */
+    let buffer = js_sys::ArrayBuffer::new(16);
+    let echoed = client.echo(buffer.clone().into()).await;
+    assert_eq!(buffer.byte_length(), 0);
+    assert_eq!(echoed.into_array_buffer().byte_length(), 16);
+    /* bytes can be used inside other serialized types */
+    let upload = Upload { name: String::from("cat.png"), contents: vec![0; 42].into() };
+    assert_eq!(client.upload(upload).await, "cat.png: 42 bytes");
+}
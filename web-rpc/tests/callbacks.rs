@@ -0,0 +1,83 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_test::*;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    path: String,
+    size: u32,
+}
+
+#[web_rpc::service]
+pub trait Watcher {
+    #[remote(on_change)]
+    fn watch(path: String, on_change: web_rpc::Callback<ChangeEvent>);
+    fn write(path: String, size: u32);
+    fn unwatch(path: String);
+}
+
+#[derive(Default)]
+struct WatcherServiceImpl {
+    watchers: RefCell<Vec<(String, web_rpc::Callback<ChangeEvent>)>>,
+}
+impl Watcher for WatcherServiceImpl {
+    fn watch(&self, path: String, on_change: web_rpc::Callback<ChangeEvent>) {
+        self.watchers.borrow_mut().push((path, on_change));
+    }
+    fn write(&self, path: String, size: u32) {
+        for (_, on_change) in self.watchers.borrow().iter().filter(|(watched, _)| *watched == path) {
+            on_change.call(ChangeEvent { path: path.clone(), size });
+        }
+    }
+    fn unwatch(&self, path: String) {
+        self.watchers.borrow_mut().retain(|(watched, _)| *watched != path);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn callbacks() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<WatcherService<_>>(WatcherServiceImpl::default())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WatcherClient>()
+        .build();
+    /* the callback runs in the calling context each time the service calls it */
+    let events: Rc<RefCell<Vec<ChangeEvent>>> = Default::default();
+    let events_cloned = events.clone();
+    client.watch(String::from("a.txt"), web_rpc::Callback::new(move |event| {
+        events_cloned.borrow_mut().push(event);
+    }));
+    client.write(String::from("a.txt"), 1);
+    client.write(String::from("b.txt"), 2);
+    client.write(String::from("a.txt"), 3);
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert_eq!(*events.borrow(), [
+        ChangeEvent { path: String::from("a.txt"), size: 1 },
+        ChangeEvent { path: String::from("a.txt"), size: 3 },
+    ]);
+    /* the closure is dropped once the service drops the callback */
+    assert_eq!(Rc::strong_count(&events), 2);
+    client.unwatch(String::from("a.txt"));
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert_eq!(Rc::strong_count(&events), 1);
+    /* callbacks that are dropped without being sent are released */
+    let unsent = web_rpc::Callback::new({
+        let events = events.clone();
+        move |event: ChangeEvent| events.borrow_mut().push(event)
+    });
+    drop(unsent);
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert_eq!(Rc::strong_count(&events), 1);
+}
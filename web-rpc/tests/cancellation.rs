@@ -0,0 +1,98 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service(cooperative)]
+pub trait Import {
+    async fn import(items: u32) -> u32;
+}
+
+#[derive(Default)]
+struct ImportServiceImpl {
+    log: RefCell<Vec<String>>,
+}
+impl Import for ImportServiceImpl {
+    async fn import(&self, token: &web_rpc::CancellationToken, items: u32) -> u32 {
+        for item in 0..items {
+            if token.is_cancelled() {
+                /* clean up and return the partial result */
+                self.log.borrow_mut().push(format!("rolled back after {item} items"));
+                return item;
+            }
+            gloo_timers::future::sleep(Duration::from_millis(10)).await;
+        }
+        items
+    }
+}
+
+#[web_rpc::service]
+pub trait Sleep {
+    async fn sleep(interval: Duration) -> u32;
+}
+
+struct SleepServiceImpl;
+impl Sleep for SleepServiceImpl {
+    async fn sleep(&self, interval: Duration) -> u32 {
+        gloo_timers::future::sleep(interval).await;
+        42
+    }
+}
+
+#[wasm_bindgen_test]
+async fn cooperative() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<ImportServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<ImportService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<ImportClient>()
+        .build();
+    /* run test, the method returns a partial result once cancelled */
+    let import = client.import(100);
+    gloo_timers::future::sleep(Duration::from_millis(55)).await;
+    import.cancel();
+    let items = import.await;
+    assert!(items > 0 && items < 100);
+    assert_eq!(*service_impl.log.borrow(), [format!("rolled back after {items} items")]);
+    /* requests that complete are not affected */
+    assert_eq!(client.import(2).await, 2);
+    assert_eq!(client.stats().errors, 0);
+}
+
+#[wasm_bindgen_test]
+async fn non_cooperative() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<SleepService<_>>(SleepServiceImpl)
+        .build();
+    let server_handle = server.handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<SleepClient>()
+        .build();
+    /* run test, the method is dropped and the request fails */
+    let sleep = client.sleep(Duration::from_secs(10)).into_result();
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    sleep.cancel();
+    assert_eq!(sleep.await, Err(web_rpc::Error::Cancelled));
+    let server_stats = server_handle.stats();
+    assert_eq!((server_stats.aborts, server_stats.errors), (1, 1));
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Calculator {
+    fn add(left: u32, right: u32) -> u32;
+}
+struct CalculatorServiceImpl;
+impl Calculator for CalculatorServiceImpl {
+    fn add(&self, left: u32, right: u32) -> u32 {
+        left + right
+    }
+}
+
+#[web_rpc::service]
+pub trait Greeter {
+    fn greet(name: String) -> String;
+}
+struct GreeterServiceImpl(&'static str);
+impl Greeter for GreeterServiceImpl {
+    fn greet(&self, name: String) -> String {
+        format!("{}, {name}!", self.0)
+    }
+}
+
+#[wasm_bindgen_test]
+async fn channels() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* open virtual channels, these do not require a handshake */
+    let calculator_channel = client_interface.open_channel();
+    let greeter_channel = client_interface.open_channel();
+    /* accept the virtual channels on the other side in the order in which they were opened */
+    let calculator_server = web_rpc::Builder::new(server_interface.accept_channel().await)
+        .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(calculator_server);
+    let greeter_server = web_rpc::Builder::new(server_interface.accept_channel().await)
+        .with_service::<GreeterService<_>>(GreeterServiceImpl("Hello"))
+        .build();
+    wasm_bindgen_futures::spawn_local(greeter_server);
+    /* channels can be opened from both sides */
+    let reverse_channel = server_interface.open_channel();
+    let reverse_server = web_rpc::Builder::new(client_interface.accept_channel().await)
+        .with_service::<GreeterService<_>>(GreeterServiceImpl("Bonjour"))
+        .build();
+    wasm_bindgen_futures::spawn_local(reverse_server);
+    /* the interface itself remains usable */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create clients */
+    let calculator_client = web_rpc::Builder::new(calculator_channel)
+        .with_client::<CalculatorClient>()
+        .build();
+    let greeter_client = web_rpc::Builder::new(greeter_channel)
+        .with_client::<GreeterClient>()
+        .build();
+    let reverse_client = web_rpc::Builder::new(reverse_channel)
+        .with_client::<GreeterClient>()
+        .build();
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<CalculatorClient>()
+        .build();
+    /* run test */
+    assert_eq!(calculator_client.add(41, 1).await, 42);
+    assert_eq!(greeter_client.greet(String::from("Alice")).await, "Hello, Alice!");
+    assert_eq!(reverse_client.greet(String::from("Bob")).await, "Bonjour, Bob!");
+    assert_eq!(client.add(1, 2).await, 3);
+}
+
+#[wasm_bindgen_test]
+async fn closed_channels() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create a server and a client on a virtual channel */
+    let calculator_channel = client_interface.open_channel();
+    let (calculator_server, calculator_server_handle) = web_rpc::Builder::new(server_interface.accept_channel().await)
+        .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(calculator_server);
+    let calculator_client = web_rpc::Builder::new(calculator_channel)
+        .with_client::<CalculatorClient>()
+        .build();
+    assert_eq!(calculator_client.add(41, 1).await, 42);
+    /* dropping the server closes its side of the channel, which fails the client's requests */
+    drop(calculator_server_handle);
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert_eq!(calculator_client.add(1, 2).into_result().await, Err(web_rpc::Error::Shutdown));
+}
+
+#[wasm_bindgen_test]
+async fn closed_client_channels() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create a server and a client on a virtual channel */
+    let calculator_channel = client_interface.open_channel();
+    let (calculator_server, calculator_server_done) = web_rpc::Builder::new(server_interface.accept_channel().await)
+        .with_service::<CalculatorService<_>>(CalculatorServiceImpl)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(calculator_server);
+    let calculator_client = web_rpc::Builder::new(calculator_channel)
+        .with_client::<CalculatorClient>()
+        .build();
+    assert_eq!(calculator_client.add(41, 1).await, 42);
+    /* dropping the client closes its side of the channel, after which the server completes */
+    drop(calculator_client);
+    calculator_server_done.await;
+}
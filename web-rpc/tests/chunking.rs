@@ -0,0 +1,126 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Text {
+    fn reverse(text: String) -> String;
+}
+struct TextServiceImpl;
+impl Text for TextServiceImpl {
+    fn reverse(&self, text: String) -> String {
+        text.chars().rev().collect()
+    }
+}
+
+#[wasm_bindgen_test]
+async fn chunking() {
+    console_error_panic_hook::set_once();
+    /* create channel and count the messages that arrive at the server */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let received = Rc::new(Cell::new(0));
+    let received_cloned = received.clone();
+    let _counter = gloo_events::EventListener::new(&channel.port1(), "message", move |_| {
+        received_cloned.set(received_cloned.get() + 1);
+    });
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server, the server only accepts messages of up to 64 KiB */
+    let server_interface = server_interface
+        .with_chunk_size(1000)
+        .with_max_message_size(64 * 1024);
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<TextService<_>>(TextServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface.with_chunk_size(1024))
+        .with_client::<TextClient>()
+        .build();
+    /* requests and responses that exceed the chunk size are split and reassembled */
+    let handshake = received.get();
+    let text = (0..10_000).map(|index| char::from(b'a' + (index % 26) as u8)).collect::<String>();
+    assert_eq!(client.reverse(text.clone()).await, text.chars().rev().collect::<String>());
+    assert!(received.get() - handshake >= 10);
+    assert!(client.stats().bytes_received > 10_000);
+    /* small messages are not split */
+    let handshake = received.get();
+    assert_eq!(client.reverse(String::from("abc")).await, "cba");
+    assert_eq!(received.get() - handshake, 1);
+    /* messages that exceed the maximum size once reassembled are discarded */
+    let response = client.with_timeout(Duration::from_millis(100))
+        .reverse("a".repeat(100 * 1024))
+        .into_result()
+        .await;
+    assert_eq!(response, Err(web_rpc::Error::Timeout));
+    /* the port remains usable afterwards */
+    assert_eq!(client.reverse(String::from("xyz")).await, "zyx");
+}
+
+#[wasm_bindgen_test]
+async fn chunking_with_batching() {
+    console_error_panic_hook::set_once();
+    /* create channel and count the messages that arrive at the server */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let received = Rc::new(Cell::new(0));
+    let received_cloned = received.clone();
+    let _counter = gloo_events::EventListener::new(&channel.port1(), "message", move |_| {
+        received_cloned.set(received_cloned.get() + 1);
+    });
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<TextService<_>>(TextServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client, which both batches and chunks its messages */
+    let client_interface = client_interface
+        .with_batching(web_rpc::interface::Batching::Microtask)
+        .with_chunk_size(1024);
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<TextClient>()
+        .build();
+    /* the small request is batched and posted before the chunks of the large request, which are
+       posted as separate messages */
+    let handshake = received.get();
+    let text = (0..10_000).map(|index| char::from(b'a' + (index % 26) as u8)).collect::<String>();
+    let responses = futures_util::future::join3(
+        client.reverse(String::from("abc")),
+        client.reverse(text.clone()),
+        client.reverse(String::from("xyz")),
+    ).await;
+    assert_eq!(responses, (String::from("cba"), text.chars().rev().collect::<String>(), String::from("zyx")));
+    assert!(received.get() - handshake >= 11);
+}
+
+#[wasm_bindgen_test]
+async fn max_message_size() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server, the server only accepts messages of up to 1 KiB */
+    let server = web_rpc::Builder::new(server_interface.with_max_message_size(1024))
+        .with_service::<TextService<_>>(TextServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client, which does not split its messages */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<TextClient>()
+        .build();
+    /* messages that were not chunked are also discarded if they exceed the maximum size */
+    let response = client.with_timeout(Duration::from_millis(100))
+        .reverse("a".repeat(2048))
+        .into_result()
+        .await;
+    assert_eq!(response, Err(web_rpc::Error::Timeout));
+    assert_eq!(client.reverse(String::from("abc")).await, "cba");
+}
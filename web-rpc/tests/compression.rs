@@ -0,0 +1,73 @@
+#![cfg(any(feature = "lz4", feature = "deflate"))]
+
+use std::{cell::RefCell, rc::Rc};
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_rpc::interface::Compression;
+
+#[web_rpc::service]
+pub trait Documents {
+    fn load(len: usize) -> String;
+    fn store(document: String) -> usize;
+}
+struct DocumentsServiceImpl;
+impl Documents for DocumentsServiceImpl {
+    fn load(&self, len: usize) -> String {
+        "{\"key\": \"value\"}".repeat(len)
+    }
+    fn store(&self, document: String) -> usize {
+        document.len()
+    }
+}
+
+async fn compression(compression: Compression, name: &'static str) {
+    /* create channel and record the posted messages that arrive at the server */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let received: Rc<RefCell<Vec<js_sys::Array>>> = Default::default();
+    let received_cloned = received.clone();
+    let _recorder = gloo_events::EventListener::new(&channel.port1(), "message", move |event| {
+        let message = event.unchecked_ref::<web_sys::MessageEvent>().data();
+        if let Ok(message) = message.dyn_into::<js_sys::Array>() {
+            received_cloned.borrow_mut().push(message);
+        }
+    });
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface.with_compression(compression, 1024))
+        .with_service::<DocumentsService<_>>(DocumentsServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface.with_compression(compression, 1024))
+        .with_client::<DocumentsClient>()
+        .build();
+    /* messages above the threshold are compressed and start with the name of the compression */
+    let document = client.load(10_000).await;
+    assert_eq!(document.len(), 160_000);
+    assert_eq!(client.store(document).await, 160_000);
+    let message = received.borrow_mut().pop().unwrap();
+    assert_eq!(message.get(0).as_string().as_deref(), Some(name));
+    assert!(message.get(1).unchecked_into::<js_sys::ArrayBuffer>().byte_length() < 160_000 / 10);
+    /* messages below the threshold are not compressed */
+    assert_eq!(client.store(String::from("{}")).await, 2);
+    let message = received.borrow_mut().pop().unwrap();
+    assert!(message.get(0).is_instance_of::<js_sys::ArrayBuffer>());
+}
+
+#[cfg(feature = "lz4")]
+#[wasm_bindgen_test]
+async fn lz4() {
+    console_error_panic_hook::set_once();
+    compression(Compression::Lz4, "lz4").await;
+}
+
+#[cfg(feature = "deflate")]
+#[wasm_bindgen_test]
+async fn deflate() {
+    console_error_panic_hook::set_once();
+    compression(Compression::Deflate, "deflate").await;
+}
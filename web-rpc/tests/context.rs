@@ -0,0 +1,73 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service(context)]
+pub trait Job {
+    fn info() -> (usize, Option<String>);
+    async fn remaining() -> Option<Duration>;
+    async fn run(interval: Duration) -> u32;
+}
+
+#[derive(Default)]
+struct JobServiceImpl {
+    cancelled: Rc<Cell<bool>>,
+}
+impl Job for JobServiceImpl {
+    fn info(&self, ctx: &web_rpc::RequestContext) -> (usize, Option<String>) {
+        (ctx.seq_id(), ctx.metadata().get("user").cloned())
+    }
+    async fn remaining(&self, ctx: &web_rpc::RequestContext) -> Option<Duration> {
+        ctx.remaining()
+    }
+    async fn run(&self, ctx: &web_rpc::RequestContext, interval: Duration) -> u32 {
+        /* observe the cancellation from a separate task since this future is dropped on abort */
+        let context = web_rpc::RequestContext::current().unwrap();
+        let cancelled = self.cancelled.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            context.cancelled().await;
+            cancelled.set(true);
+        });
+        assert!(!ctx.is_cancelled());
+        gloo_timers::future::sleep(interval).await;
+        42
+    }
+}
+
+#[wasm_bindgen_test]
+async fn context() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<JobServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<JobService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<JobClient>()
+        .build();
+    /* run test */
+    assert_eq!(client.info().await, (0, None));
+    assert_eq!(client.with_metadata("user", "alice").info().await, (1, Some(String::from("alice"))));
+    assert_eq!(client.remaining().await, None);
+    let remaining = client.with_timeout(Duration::from_secs(10)).remaining().await.unwrap();
+    assert!(remaining > Duration::from_secs(9) && remaining <= Duration::from_secs(10));
+    /* the request is aborted once the timeout elapses */
+    let response = client.with_timeout(Duration::from_millis(10))
+        .run(Duration::from_secs(10))
+        .into_result()
+        .await;
+    assert_eq!(response, Err(web_rpc::Error::Timeout));
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert!(service_impl.cancelled.get());
+    assert_eq!(client.stats().aborts, 1);
+    /* requests complete normally within the timeout */
+    assert_eq!(client.with_timeout(Duration::from_secs(10)).run(Duration::ZERO).await, 42);
+}
@@ -0,0 +1,96 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use futures_channel::oneshot;
+use futures_util::{future::RemoteHandle, FutureExt};
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Documents {
+    async fn write(#[order_key] document: u32, value: u32);
+    async fn read(#[order_key] document: u32) -> Vec<u32>;
+}
+
+#[derive(Default)]
+struct DocumentsServiceImpl {
+    log: RefCell<Vec<(u32, u32)>>,
+    /* writes with a gate wait until the test opens it */
+    gates: RefCell<HashMap<(u32, u32), oneshot::Receiver<()>>>,
+}
+impl DocumentsServiceImpl {
+    fn gate(&self, document: u32, value: u32) -> oneshot::Sender<()> {
+        let (gate_tx, gate_rx) = oneshot::channel();
+        self.gates.borrow_mut().insert((document, value), gate_rx);
+        gate_tx
+    }
+}
+impl Documents for DocumentsServiceImpl {
+    async fn write(&self, document: u32, value: u32) {
+        let gate = self.gates.borrow_mut().remove(&(document, value));
+        if let Some(gate) = gate {
+            gate.await.unwrap();
+        }
+        self.log.borrow_mut().push((document, value));
+    }
+    async fn read(&self, document: u32) -> Vec<u32> {
+        self.log.borrow()
+            .iter()
+            .filter_map(|(log_document, value)| (*log_document == document).then_some(*value))
+            .collect()
+    }
+}
+
+async fn setup(
+    execution: web_rpc::server::Execution
+) -> (DocumentsClient, Rc<DocumentsServiceImpl>, RemoteHandle<()>) {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when the returned handle is dropped) */
+    let service_impl: Rc<DocumentsServiceImpl> = Default::default();
+    let (server, server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<DocumentsService<_>>(service_impl.clone())
+        .with_execution(execution)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<DocumentsClient>()
+        .build();
+    (client, service_impl, server_handle)
+}
+
+#[wasm_bindgen_test]
+async fn sequential() {
+    let (client, service_impl, _server_handle) = setup(web_rpc::server::Execution::Sequential).await;
+    let first = service_impl.gate(1, 1);
+    let second = service_impl.gate(2, 1);
+    client.write(1, 1);
+    client.write(2, 1);
+    client.write(1, 2);
+    /* the second write cannot overtake the first write even though it is released first */
+    second.send(()).unwrap();
+    first.send(()).unwrap();
+    assert_eq!(client.read(1).await, [1, 2]);
+    assert_eq!(*service_impl.log.borrow(), [(1, 1), (2, 1), (1, 2)]);
+}
+
+#[wasm_bindgen_test]
+async fn keyed() {
+    let (client, service_impl, _server_handle) = setup(web_rpc::server::Execution::Keyed).await;
+    let first = service_impl.gate(1, 1);
+    let second = service_impl.gate(2, 1);
+    client.write(1, 1);
+    client.write(2, 1);
+    client.write(1, 2);
+    /* the write to the second document completes while the first document is blocked */
+    second.send(()).unwrap();
+    assert_eq!(client.read(2).await, [1]);
+    first.send(()).unwrap();
+    assert_eq!(client.read(1).await, [1, 2]);
+    assert_eq!(*service_impl.log.borrow(), [(2, 1), (1, 1), (1, 2)]);
+}
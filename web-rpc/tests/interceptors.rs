@@ -0,0 +1,153 @@
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::Rc};
+
+use futures_util::future::LocalBoxFuture;
+use wasm_bindgen_test::*;
+use web_rpc::{client::{self, Interceptor}, server::{self, Layer}};
+
+#[web_rpc::service(derive(Clone))]
+pub trait Store {
+    fn get(key: u32) -> u32;
+    fn flaky() -> u32;
+    #[post(return)]
+    fn label(key: u32) -> js_sys::JsString;
+}
+
+struct StoreServiceImpl;
+impl Store for StoreServiceImpl {
+    fn get(&self, key: u32) -> u32 {
+        key * 2
+    }
+    fn flaky(&self) -> u32 {
+        42
+    }
+    fn label(&self, key: u32) -> js_sys::JsString {
+        js_sys::JsString::from(format!("key {key}"))
+    }
+}
+
+/* a server layer that rejects every other request to flaky */
+#[derive(Default)]
+struct Flaky {
+    calls: Cell<u32>,
+}
+impl Layer<StoreRequest, StoreResponse> for Flaky {
+    fn call<'a>(
+        &'a self,
+        context: server::Context,
+        request: StoreRequest,
+        next: server::Next<'a, StoreRequest, StoreResponse>
+    ) -> LocalBoxFuture<'a, Result<StoreResponse, web_rpc::Error>> {
+        Box::pin(async move {
+            if context.method == "flaky" {
+                self.calls.set(self.calls.get() + 1);
+                if self.calls.get() % 2 == 1 {
+                    return Err(web_rpc::Error::Rejected(String::from("try again")));
+                }
+            }
+            next.run(request).await
+        })
+    }
+}
+
+/* an interceptor that answers some requests without sending them */
+struct Cache;
+impl Interceptor<StoreRequest, StoreResponse> for Cache {
+    fn call<'a>(
+        &'a self,
+        _context: client::Context,
+        request: StoreRequest,
+        next: client::Next<'a, StoreRequest, StoreResponse>
+    ) -> LocalBoxFuture<'a, Result<StoreResponse, web_rpc::Error>> {
+        Box::pin(async move {
+            match request {
+                StoreRequest::Get { key: 7 } => Ok(StoreResponse::Get(0)),
+                StoreRequest::Label { key: 7 } => Ok(StoreResponse::Label(())),
+                request => next.run(request).await,
+            }
+        })
+    }
+}
+
+/* an interceptor that retries rejected requests once */
+struct Retry;
+impl Interceptor<StoreRequest, StoreResponse> for Retry {
+    fn call<'a>(
+        &'a self,
+        _context: client::Context,
+        request: StoreRequest,
+        next: client::Next<'a, StoreRequest, StoreResponse>
+    ) -> LocalBoxFuture<'a, Result<StoreResponse, web_rpc::Error>> {
+        Box::pin(async move {
+            match next.run(request.clone()).await {
+                Err(web_rpc::Error::Rejected(_)) => next.run(request).await,
+                response => response,
+            }
+        })
+    }
+}
+
+/* an interceptor that works for any client and logs the requests that were sent */
+struct Log {
+    entries: Rc<RefCell<Vec<String>>>,
+}
+impl<Request: 'static, Response: 'static> Interceptor<Request, Response> for Log {
+    fn call<'a>(
+        &'a self,
+        context: client::Context,
+        request: Request,
+        next: client::Next<'a, Request, Response>
+    ) -> LocalBoxFuture<'a, Result<Response, web_rpc::Error>> {
+        Box::pin(async move {
+            let response = next.run(request).await;
+            let sent = next.sent().unwrap();
+            assert!(sent.size > 0);
+            let outcome = match response {
+                Ok(_) => "ok",
+                Err(_) => "error",
+            };
+            self.entries.borrow_mut().push(format!("{} {} {outcome}", context.method, sent.seq_id));
+            response
+        })
+    }
+}
+
+#[wasm_bindgen_test]
+async fn interceptors() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<StoreService<_>>(StoreServiceImpl)
+        .with_layer(Flaky::default())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client, the interceptor that was added last is called first */
+    let entries: Rc<RefCell<Vec<String>>> = Default::default();
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<StoreClient>()
+        .with_interceptor(Log { entries: entries.clone() })
+        .with_interceptor(Retry)
+        .with_interceptor(Cache)
+        .build();
+    /* run test */
+    assert_eq!(client.get(7).await, 0);
+    assert_eq!(client.get(3).await, 6);
+    assert_eq!(client.flaky().await, 42);
+    assert_eq!(*entries.borrow(), [
+        "get 0 ok",
+        "flaky 1 error",
+        "flaky 2 ok",
+    ]);
+    let stats = client.stats();
+    assert_eq!(stats.requests, HashMap::from([("get", 1), ("flaky", 2)]));
+    assert_eq!(stats.errors, 0);
+    /* responses with posted return values can only be received from the server */
+    assert_eq!(client.label(3).await, "key 3");
+    assert!(matches!(client.label(7).into_result().await, Err(web_rpc::Error::Rejected(_))));
+    assert_eq!(client.stats().errors, 1);
+}
@@ -0,0 +1,56 @@
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Text {
+    fn len(text: String) -> usize;
+    fn reverse(text: String) -> String;
+}
+struct TextServiceImpl;
+impl Text for TextServiceImpl {
+    fn len(&self, text: String) -> usize {
+        text.len()
+    }
+    fn reverse(&self, text: String) -> String {
+        text.chars().rev().collect()
+    }
+}
+
+#[wasm_bindgen_test]
+async fn large_messages() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<TextService<_>>(TextServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<TextClient>()
+        .build();
+    /* messages that span several chunks are encoded and decoded correctly */
+    let text = (0..3 * 64 * 1024 + 7).map(|index| char::from(b'a' + (index % 26) as u8)).collect::<String>();
+    assert_eq!(client.reverse(text.clone()).await, text.chars().rev().collect::<String>());
+    #[cfg(feature = "copy-stats")]
+    copies(&client).await;
+}
+
+/* each message is copied exactly once between Rust and Javascript memory when it is encoded and
+   once when it is decoded, but never as a whole */
+#[cfg(feature = "copy-stats")]
+async fn copies(client: &TextClient) {
+    const SIZE: usize = 16 * 1024 * 1024;
+    let (stats, copied) = (client.stats(), web_rpc::copied());
+    assert_eq!(client.len("a".repeat(SIZE)).await, SIZE);
+    let sent = client.stats().bytes_sent - stats.bytes_sent;
+    let received = client.stats().bytes_received - stats.bytes_received;
+    assert!(sent as usize > SIZE);
+    /* the client and the server run on the same thread, so both of them copy each message */
+    assert_eq!(web_rpc::copied().bytes - copied.bytes, 2 * (sent + received));
+    assert!(web_rpc::copied().largest <= 64 * 1024);
+}
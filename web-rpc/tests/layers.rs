@@ -0,0 +1,99 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures_util::future::LocalBoxFuture;
+use wasm_bindgen_test::*;
+use web_rpc::server::{Context, Layer, Next};
+
+#[web_rpc::service]
+pub trait Vault {
+    fn read(key: String) -> u32;
+    #[post(return)]
+    fn label() -> js_sys::JsString;
+}
+
+struct VaultServiceImpl;
+impl Vault for VaultServiceImpl {
+    fn read(&self, key: String) -> u32 {
+        key.len() as u32
+    }
+    fn label(&self) -> js_sys::JsString {
+        "vault".into()
+    }
+}
+
+/* a layer that works for any service */
+struct Log {
+    name: &'static str,
+    entries: Rc<RefCell<Vec<String>>>,
+}
+impl<Request: 'static, Response: 'static> Layer<Request, Response> for Log {
+    fn call<'a>(
+        &'a self,
+        context: Context,
+        request: Request,
+        next: Next<'a, Request, Response>
+    ) -> LocalBoxFuture<'a, Result<Response, web_rpc::Error>> {
+        Box::pin(async move {
+            self.entries.borrow_mut().push(format!("{} {}", self.name, context.method));
+            next.run(request).await
+        })
+    }
+}
+
+/* a layer that inspects the requests of a specific service */
+struct Auth;
+impl Layer<VaultRequest, VaultResponse> for Auth {
+    fn call<'a>(
+        &'a self,
+        _context: Context,
+        request: VaultRequest,
+        next: Next<'a, VaultRequest, VaultResponse>
+    ) -> LocalBoxFuture<'a, Result<VaultResponse, web_rpc::Error>> {
+        Box::pin(async move {
+            match &request {
+                VaultRequest::Read { key } if key == "secret" =>
+                    Err(web_rpc::Error::Rejected(String::from("access denied"))),
+                _ => next.run(request).await,
+            }
+        })
+    }
+}
+
+#[wasm_bindgen_test]
+async fn layers() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server, the layer that was added last is called first */
+    let entries: Rc<RefCell<Vec<String>>> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<VaultService<_>>(VaultServiceImpl)
+        .with_layer(Log { name: "inner", entries: entries.clone() })
+        .with_layer(Auth)
+        .with_layer(Log { name: "outer", entries: entries.clone() })
+        .build();
+    let server_handle = server.handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<VaultClient>()
+        .build();
+    /* run test */
+    assert_eq!(client.read(String::from("public")).await, 6);
+    assert_eq!(
+        client.read(String::from("secret")).into_result().await,
+        Err(web_rpc::Error::Rejected(String::from("access denied")))
+    );
+    /* posted return values pass through the layers */
+    assert_eq!(client.label().await, "vault");
+    assert_eq!(*entries.borrow(), [
+        "outer read", "inner read",
+        "outer read",
+        "outer label", "inner label",
+    ]);
+    assert_eq!(server_handle.stats().errors, 1);
+}
@@ -0,0 +1,90 @@
+use std::{cell::RefCell, rc::Rc};
+
+use futures_util::future::LocalBoxFuture;
+use wasm_bindgen_test::*;
+use web_rpc::client::{self, Interceptor};
+
+#[web_rpc::service]
+pub trait Greeter {
+    async fn greet(name: String) -> String;
+    fn log(message: String);
+}
+
+#[derive(Default)]
+struct GreeterServiceImpl {
+    logs: RefCell<Vec<String>>,
+}
+impl Greeter for GreeterServiceImpl {
+    async fn greet(&self, name: String) -> String {
+        /* the context is available across await points */
+        gloo_timers::future::sleep(std::time::Duration::from_millis(10)).await;
+        let context = web_rpc::RequestContext::current().unwrap();
+        context.set_response_metadata("served-by", "greeter");
+        match context.metadata().get("locale").map(String::as_str) {
+            Some("fr") => format!("Bonjour, {name}!"),
+            _ => format!("Hello, {name}!"),
+        }
+    }
+    fn log(&self, message: String) {
+        let context = web_rpc::RequestContext::current().unwrap();
+        let trace_id = context.metadata().get("trace-id").cloned().unwrap_or_default();
+        self.logs.borrow_mut().push(format!("{trace_id}: {message}"));
+    }
+}
+
+/* an interceptor that adds a trace id to every request and records the response metadata */
+struct Trace {
+    served_by: Rc<RefCell<Vec<String>>>,
+}
+impl Interceptor<GreeterRequest, GreeterResponse> for Trace {
+    fn call<'a>(
+        &'a self,
+        _context: client::Context,
+        request: GreeterRequest,
+        next: client::Next<'a, GreeterRequest, GreeterResponse>
+    ) -> LocalBoxFuture<'a, Result<GreeterResponse, web_rpc::Error>> {
+        Box::pin(async move {
+            next.metadata().insert(String::from("trace-id"), String::from("abc"));
+            let response = next.run(request).await;
+            let response_metadata = next.response_metadata().unwrap();
+            self.served_by.borrow_mut().push(response_metadata["served-by"].clone());
+            response
+        })
+    }
+}
+
+#[wasm_bindgen_test]
+async fn metadata() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<GreeterServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<GreeterService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let served_by: Rc<RefCell<Vec<String>>> = Default::default();
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<GreeterClient>()
+        .with_interceptor(Trace { served_by: served_by.clone() })
+        .build();
+    /* run test, metadata can be set per client or per call */
+    let french_client = client.with_metadata("locale", "fr");
+    assert_eq!(client.greet(String::from("Alice")).await, "Hello, Alice!");
+    assert_eq!(french_client.greet(String::from("Bob")).await, "Bonjour, Bob!");
+    assert_eq!(client.with_metadata("locale", "fr").greet(String::from("Eve")).await, "Bonjour, Eve!");
+    assert_eq!(*served_by.borrow(), ["greeter", "greeter", "greeter"]);
+    /* notifications are not intercepted but carry the metadata of the client */
+    client.with_metadata("trace-id", "xyz").log(String::from("started"));
+    client.log(String::from("stopped"));
+    assert_eq!(client.greet(String::from("Alice")).await, "Hello, Alice!");
+    assert_eq!(*service_impl.logs.borrow(), ["xyz: started", ": stopped"]);
+    /* there is no context outside of the server */
+    assert!(web_rpc::RequestContext::current().is_none());
+}
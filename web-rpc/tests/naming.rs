@@ -0,0 +1,52 @@
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+/* a facade crate that re-exports web_rpc under a different path */
+mod facade {
+    pub use web_rpc as rpc;
+}
+
+#[facade::rpc::service(
+    client = "Calculator",
+    service = "CalculatorServer",
+    request = "CalculatorCall",
+    response = "CalculatorReply",
+    derive(Debug, Clone, PartialEq),
+    crate = "facade::rpc"
+)]
+pub trait CalculatorApi {
+    fn add(left: u32, right: u32) -> u32;
+}
+struct CalculatorImpl;
+impl CalculatorApi for CalculatorImpl {
+    fn add(&self, left: u32, right: u32) -> u32 {
+        left + right
+    }
+}
+
+#[wasm_bindgen_test]
+async fn naming() {
+    console_error_panic_hook::set_once();
+    /* the generated types can be logged and compared */
+    let call = CalculatorCall::Add { left: 41, right: 1 };
+    assert_eq!(format!("{:?}", call.clone()), "Add { left: 41, right: 1 }");
+    assert_eq!(CalculatorReply::Add(42), CalculatorReply::Add(42));
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        facade::rpc::Interface::new(channel.port1()),
+        facade::rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let (server, _server_handle) = facade::rpc::Builder::new(server_interface)
+        .with_service::<CalculatorServer<_>>(CalculatorImpl)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = facade::rpc::Builder::new(client_interface)
+        .with_client::<Calculator>()
+        .build();
+    /* run test */
+    assert_eq!(client.add(41, 1).await, 42);
+}
@@ -0,0 +1,127 @@
+use std::{cell::{Cell, RefCell}, collections::VecDeque, rc::Rc};
+
+use futures_channel::mpsc;
+use futures_util::StreamExt;
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Counter {
+    #[ack]
+    async fn add(value: u32);
+    #[abortable]
+    async fn count(times: u32);
+    fn reset();
+}
+
+#[derive(Default)]
+struct CounterServiceImpl {
+    value: Cell<u32>,
+    /* each call of `count` takes a step whenever the test sends one and reports its progress */
+    steps: RefCell<VecDeque<(mpsc::UnboundedReceiver<()>, mpsc::UnboundedSender<u32>)>>,
+}
+impl CounterServiceImpl {
+    fn steps(&self) -> (mpsc::UnboundedSender<()>, mpsc::UnboundedReceiver<u32>) {
+        let (steps_tx, steps_rx) = mpsc::unbounded();
+        let (progress_tx, progress_rx) = mpsc::unbounded();
+        self.steps.borrow_mut().push_back((steps_rx, progress_tx));
+        (steps_tx, progress_rx)
+    }
+}
+impl Counter for CounterServiceImpl {
+    async fn add(&self, value: u32) {
+        self.value.set(self.value.get() + value);
+    }
+    async fn count(&self, times: u32) {
+        let (mut steps_rx, progress_tx) = self.steps.borrow_mut().pop_front().unwrap();
+        for _ in 0..times {
+            steps_rx.next().await.unwrap();
+            self.value.set(self.value.get() + 1);
+            progress_tx.unbounded_send(self.value.get()).unwrap();
+        }
+    }
+    fn reset(&self) {
+        self.value.set(0);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn notifications() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<CounterServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<CounterService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<CounterClient>()
+        .build();
+    /* acknowledged notifications resolve once the server has executed them */
+    client.add(5).await;
+    assert_eq!(service_impl.value.get(), 5);
+    client.reset();
+    /* dropping the handle of an abortable notification aborts it, which drops the method and
+       with it the progress channel */
+    let (steps_tx, mut progress_rx) = service_impl.steps();
+    let count = client.count(100);
+    steps_tx.unbounded_send(()).unwrap();
+    assert_eq!(progress_rx.next().await, Some(1));
+    drop(count);
+    assert_eq!(progress_rx.next().await, None);
+    assert!(steps_tx.unbounded_send(()).is_err());
+    assert_eq!(service_impl.value.get(), 1);
+    /* detached notifications run to completion */
+    client.reset();
+    let (steps_tx, progress_rx) = service_impl.steps();
+    for _ in 0..3 {
+        steps_tx.unbounded_send(()).unwrap();
+    }
+    client.count(3).detach();
+    assert_eq!(progress_rx.collect::<Vec<_>>().await, [1, 2, 3]);
+    assert_eq!(service_impl.value.get(), 3);
+    assert_eq!(client.stats().aborts, 1);
+}
+
+#[wasm_bindgen_test]
+async fn abortable_notifications_release_permits() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<CounterServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<CounterService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<CounterClient>()
+        .with_client_max_in_flight(1)
+        .build();
+    /* the second notification waits for the permit of the first one, which is released once
+       the first notification completes even though its handle is kept */
+    let (first_steps_tx, first_progress_rx) = service_impl.steps();
+    let (second_steps_tx, second_progress_rx) = service_impl.steps();
+    for _ in 0..2 {
+        first_steps_tx.unbounded_send(()).unwrap();
+        second_steps_tx.unbounded_send(()).unwrap();
+    }
+    let _first = client.count(2);
+    let _second = client.count(2);
+    assert_eq!(first_progress_rx.collect::<Vec<_>>().await, [1, 2]);
+    assert_eq!(second_progress_rx.collect::<Vec<_>>().await, [3, 4]);
+    assert_eq!(service_impl.value.get(), 4);
+    client.add(1).await;
+    assert_eq!(service_impl.value.get(), 5);
+}
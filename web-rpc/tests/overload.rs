@@ -0,0 +1,112 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Worker {
+    async fn work(interval: Duration) -> u32;
+}
+struct WorkerServiceImpl;
+impl Worker for WorkerServiceImpl {
+    async fn work(&self, interval: Duration) -> u32 {
+        gloo_timers::future::sleep(interval).await;
+        42
+    }
+}
+
+#[web_rpc::service]
+pub trait Forwarder {
+    async fn forward(value: u32) -> u32;
+}
+struct ForwarderServiceImpl {
+    doubler: Rc<RefCell<Option<DoublerClient>>>,
+}
+impl Forwarder for ForwarderServiceImpl {
+    async fn forward(&self, value: u32) -> u32 {
+        let doubler = self.doubler.borrow().clone().unwrap();
+        doubler.double(value).await
+    }
+}
+
+#[web_rpc::service]
+pub trait Doubler {
+    fn double(value: u32) -> u32;
+}
+struct DoublerServiceImpl;
+impl Doubler for DoublerServiceImpl {
+    fn double(&self, value: u32) -> u32 {
+        value * 2
+    }
+}
+
+#[wasm_bindgen_test]
+async fn overload() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let (server, _server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(WorkerServiceImpl)
+        .with_method_limit("work", 1)
+        .with_queue_capacity(1)
+        .with_overflow(web_rpc::Overflow::Reject)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .build();
+    /* run test, the first request is executed, the second request is queued,
+       and the third request is rejected */
+    let responses = futures_util::future::join3(
+        client.work(Duration::from_millis(50)).into_result(),
+        client.work(Duration::from_millis(50)).into_result(),
+        client.work(Duration::from_millis(50)).into_result(),
+    ).await;
+    assert_eq!(responses, (Ok(42), Ok(42), Err(web_rpc::Error::Overloaded)));
+}
+
+#[wasm_bindgen_test]
+async fn wait() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (interface1, interface2) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create the forwarder, which calls back to the other side while its queue is full */
+    let doubler: Rc<RefCell<Option<DoublerClient>>> = Default::default();
+    let (doubler_client, forwarder_server) = web_rpc::Builder::new(interface1)
+        .with_service::<ForwarderService<_>>(ForwarderServiceImpl { doubler: doubler.clone() })
+        .with_method_limit("forward", 1)
+        .with_queue_capacity(1)
+        .with_overflow(web_rpc::Overflow::Wait)
+        .with_client::<DoublerClient>()
+        .build();
+    doubler.replace(Some(doubler_client));
+    /* create the doubler */
+    let (forwarder_client, doubler_server) = web_rpc::Builder::new(interface2)
+        .with_service::<DoublerService<_>>(DoublerServiceImpl)
+        .with_client::<ForwarderClient>()
+        .build();
+    /* spawn the servers */
+    let (forwarder_server, _forwarder_server_handle) = forwarder_server.remote_handle();
+    let (doubler_server, _doubler_server_handle) = doubler_server.remote_handle();
+    wasm_bindgen_futures::spawn_local(forwarder_server);
+    wasm_bindgen_futures::spawn_local(doubler_server);
+    /* run test, the third request waits for space in the queue while the response to the call
+       of the first request is still received */
+    let responses = futures_util::future::join3(
+        forwarder_client.forward(1),
+        forwarder_client.forward(2),
+        forwarder_client.forward(3),
+    ).await;
+    assert_eq!(responses, (2, 4, 6));
+}
@@ -0,0 +1,87 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Worker {
+    async fn work(interval: Duration) -> u32;
+}
+
+#[derive(Default)]
+struct WorkerServiceImpl {
+    in_flight: Cell<u32>,
+    max_in_flight: Cell<u32>,
+}
+impl Worker for WorkerServiceImpl {
+    async fn work(&self, interval: Duration) -> u32 {
+        self.in_flight.set(self.in_flight.get() + 1);
+        self.max_in_flight.set(self.max_in_flight.get().max(self.in_flight.get()));
+        gloo_timers::future::sleep(interval).await;
+        self.in_flight.set(self.in_flight.get() - 1);
+        42
+    }
+}
+
+#[wasm_bindgen_test]
+async fn permits() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let service_impl: Rc<WorkerServiceImpl> = Default::default();
+    let (server, _server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(service_impl.clone())
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client that waits for permits */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .with_client_max_in_flight(2)
+        .build();
+    /* run test, the requests are sent two at a time */
+    let responses = futures_util::future::join4(
+        client.work(Duration::from_millis(20)),
+        client.work(Duration::from_millis(20)),
+        client.work(Duration::from_millis(20)),
+        client.work(Duration::from_millis(20)),
+    ).await;
+    assert_eq!(responses, (42, 42, 42, 42));
+    assert_eq!(service_impl.max_in_flight.get(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn fail_fast() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let (server, _server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(WorkerServiceImpl::default())
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client that rejects calls without a permit */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .with_client_max_in_flight(1)
+        .with_client_overflow(web_rpc::Overflow::Reject)
+        .build();
+    /* run test */
+    let responses = futures_util::future::join(
+        client.work(Duration::from_millis(20)).into_result(),
+        client.work(Duration::from_millis(20)).into_result(),
+    ).await;
+    assert_eq!(responses, (Ok(42), Err(web_rpc::Error::Busy)));
+    /* the permit is returned once the first request completes */
+    assert_eq!(client.work(Duration::ZERO).await, 42);
+}
@@ -0,0 +1,69 @@
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Buffers {
+    #[post(prefix, transfer(buffers), return)]
+    fn describe(
+        prefix: Option<js_sys::JsString>,
+        buffers: Vec<js_sys::ArrayBuffer>,
+        separator: String,
+    ) -> js_sys::JsString;
+    #[post(transfer(return))]
+    fn allocate(sizes: Vec<u32>) -> Vec<js_sys::ArrayBuffer>;
+    #[post(return)]
+    fn find(name: String) -> Option<js_sys::JsString>;
+}
+struct BuffersServiceImpl;
+impl Buffers for BuffersServiceImpl {
+    fn describe(
+        &self,
+        prefix: Option<js_sys::JsString>,
+        buffers: Vec<js_sys::ArrayBuffer>,
+        separator: String,
+    ) -> js_sys::JsString {
+        let sizes = buffers.iter()
+            .map(|buffer| buffer.byte_length().to_string())
+            .collect::<Vec<_>>()
+            .join(&separator);
+        let prefix = prefix.map(String::from).unwrap_or_default();
+        format!("{prefix}{sizes}").into()
+    }
+    fn allocate(&self, sizes: Vec<u32>) -> Vec<js_sys::ArrayBuffer> {
+        sizes.into_iter().map(js_sys::ArrayBuffer::new).collect()
+    }
+    fn find(&self, name: String) -> Option<js_sys::JsString> {
+        (name == "cat").then(|| js_sys::JsString::from("meow"))
+    }
+}
+
+#[wasm_bindgen_test]
+async fn post_collections() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<BuffersService<_>>(BuffersServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<BuffersClient>()
+        .build();
+    /* the values of posted collections are posted individually and each one is transferred */
+    let buffers = vec![js_sys::ArrayBuffer::new(1), js_sys::ArrayBuffer::new(2)];
+    let response = client.describe(Some("sizes: ".into()), buffers.clone(), String::from(", ")).await;
+    assert_eq!(response, "sizes: 1, 2");
+    assert!(buffers.iter().all(|buffer| buffer.byte_length() == 0));
+    let response = client.describe(None, Vec::new(), String::from(", ")).await;
+    assert_eq!(response, "");
+    /* posted collections can be returned */
+    let buffers = client.allocate(vec![4, 8, 16]).await;
+    assert_eq!(buffers.iter().map(js_sys::ArrayBuffer::byte_length).collect::<Vec<_>>(), [4, 8, 16]);
+    assert_eq!(client.find(String::from("cat")).await, Some(js_sys::JsString::from("meow")));
+    assert_eq!(client.find(String::from("dog")).await, None);
+}
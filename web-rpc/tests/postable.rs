@@ -0,0 +1,94 @@
+use wasm_bindgen_test::*;
+
+#[derive(web_rpc::Postable)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    #[post]
+    name: js_sys::JsString,
+    #[post(transfer)]
+    pixels: js_sys::ArrayBuffer,
+}
+
+#[web_rpc::service]
+pub trait Images {
+    fn rotate(image: Image) -> Image;
+    fn largest(images: Vec<Image>) -> Option<Image>;
+    #[post(label)]
+    fn describe(image: Option<Image>, label: js_sys::JsString) -> String;
+}
+
+struct ImagesServiceImpl;
+impl Images for ImagesServiceImpl {
+    fn rotate(&self, image: Image) -> Image {
+        Image {
+            width: image.height,
+            height: image.width,
+            name: image.name.concat(&"-rotated".into()),
+            pixels: image.pixels,
+        }
+    }
+    fn largest(&self, images: Vec<Image>) -> Option<Image> {
+        images.into_iter().max_by_key(|image| image.width * image.height)
+    }
+    fn describe(&self, image: Option<Image>, label: js_sys::JsString) -> String {
+        match image {
+            Some(image) => format!(
+                "{}: {} ({}x{}, {} bytes)",
+                String::from(label),
+                String::from(image.name),
+                image.width,
+                image.height,
+                image.pixels.byte_length()
+            ),
+            None => format!("{}: none", String::from(label)),
+        }
+    }
+}
+
+fn image(name: &str, width: u32, height: u32) -> Image {
+    Image {
+        width,
+        height,
+        name: name.into(),
+        pixels: js_sys::ArrayBuffer::new(width * height * 4),
+    }
+}
+
+#[wasm_bindgen_test]
+async fn postable() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<ImagesService<_>>(ImagesServiceImpl)
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<ImagesClient>()
+        .build();
+    /* posted fields are reattached on the other side and transferred fields are detached */
+    let original = image("cat", 2, 3);
+    let pixels = original.pixels.clone();
+    let rotated = client.rotate(original).await;
+    assert_eq!(pixels.byte_length(), 0);
+    assert_eq!((rotated.width, rotated.height), (3, 2));
+    assert_eq!(String::from(rotated.name), "cat-rotated");
+    assert_eq!(rotated.pixels.byte_length(), 24);
+    /* postable values can be nested in other serialized types */
+    let largest = client.largest(vec![image("a", 1, 1), image("b", 4, 4), image("c", 2, 2)]).await;
+    assert_eq!(largest.map(|image| String::from(image.name)).as_deref(), Some("b"));
+    assert!(client.largest(Vec::new()).await.is_none());
+    /* postable values can be combined with posted arguments */
+    assert_eq!(
+        client.describe(Some(image("dog", 1, 2)), "image".into()).await,
+        "image: dog (1x2, 8 bytes)"
+    );
+    assert_eq!(client.describe(None, "image".into()).await, "image: none");
+}
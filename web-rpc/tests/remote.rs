@@ -0,0 +1,111 @@
+use std::{cell::{Cell, RefCell}, rc::Rc, time::Duration};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait File {
+    fn read() -> String;
+    fn write(contents: String);
+}
+
+struct FileServiceImpl {
+    contents: RefCell<String>,
+    closed: Rc<Cell<bool>>,
+}
+impl File for FileServiceImpl {
+    fn read(&self) -> String {
+        self.contents.borrow().clone()
+    }
+    fn write(&self, contents: String) {
+        self.contents.replace(contents);
+    }
+}
+impl Drop for FileServiceImpl {
+    fn drop(&mut self) {
+        self.closed.set(true);
+    }
+}
+
+#[web_rpc::service]
+pub trait FileSystem {
+    #[remote(return)]
+    fn open(name: String) -> web_rpc::Remote<FileClient>;
+    #[remote(from, to)]
+    async fn copy(from: web_rpc::Remote<FileClient>, to: web_rpc::Remote<FileClient>);
+}
+
+#[derive(Default)]
+struct FileSystemServiceImpl {
+    closed: Rc<Cell<bool>>,
+}
+impl FileSystem for FileSystemServiceImpl {
+    fn open(&self, name: String) -> web_rpc::Remote<FileClient> {
+        web_rpc::Remote::new::<FileService<_>>(FileServiceImpl {
+            contents: RefCell::new(format!("contents of {name}")),
+            closed: self.closed.clone(),
+        })
+    }
+    async fn copy(&self, from: web_rpc::Remote<FileClient>, to: web_rpc::Remote<FileClient>) {
+        to.client().unwrap().write(from.client().unwrap().read().await);
+    }
+}
+
+#[wasm_bindgen_test]
+async fn remote() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let service_impl: Rc<FileSystemServiceImpl> = Default::default();
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<FileSystemService<_>>(service_impl.clone())
+        .build();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<FileSystemClient>()
+        .build();
+    /* returned remotes provide the clients of their objects */
+    let file = client.open(String::from("a.txt")).await;
+    assert_eq!(file.client().unwrap().read().await, "contents of a.txt");
+    file.client().unwrap().write(String::from("hello"));
+    assert_eq!(file.client().unwrap().read().await, "hello");
+    /* the object lives as long as a clone of its client */
+    let file_client = file.into_client();
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert!(!service_impl.closed.get());
+    assert_eq!(file_client.clone().read().await, "hello");
+    drop(file_client);
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert!(service_impl.closed.get());
+    /* remotes can also be passed as arguments */
+    let from_closed: Rc<Cell<bool>> = Default::default();
+    let to_contents = Rc::new(FileServiceImpl {
+        contents: Default::default(),
+        closed: Default::default(),
+    });
+    let from = web_rpc::Remote::new::<FileService<_>>(FileServiceImpl {
+        contents: RefCell::new(String::from("copied")),
+        closed: from_closed.clone(),
+    });
+    let to = web_rpc::Remote::new::<FileService<_>>(to_contents.clone());
+    client.copy(from, to);
+    gloo_timers::future::sleep(Duration::from_millis(20)).await;
+    assert_eq!(*to_contents.contents.borrow(), "copied");
+    /* the server drops the remotes once the method has completed */
+    assert!(from_closed.get());
+    /* remotes that are dropped without being sent are released */
+    let unsent_closed: Rc<Cell<bool>> = Default::default();
+    let unsent: web_rpc::Remote<FileClient> = web_rpc::Remote::new::<FileService<_>>(FileServiceImpl {
+        contents: Default::default(),
+        closed: unsent_closed.clone(),
+    });
+    assert!(unsent.client().is_none());
+    drop(unsent);
+    gloo_timers::future::sleep(Duration::from_millis(10)).await;
+    assert!(unsent_closed.get());
+}
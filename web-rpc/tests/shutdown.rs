@@ -0,0 +1,148 @@
+use std::{cell::RefCell, collections::VecDeque, time::Duration};
+
+use futures_channel::{mpsc, oneshot};
+use futures_util::{FutureExt, StreamExt};
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Worker {
+    async fn work() -> u32;
+}
+
+struct WorkerServiceImpl {
+    /* notified when a request starts, which then waits until its gate is opened */
+    started_tx: mpsc::UnboundedSender<()>,
+    gates: RefCell<VecDeque<oneshot::Receiver<()>>>,
+}
+impl WorkerServiceImpl {
+    fn new(
+        gates: impl IntoIterator<Item = oneshot::Receiver<()>>
+    ) -> (Self, mpsc::UnboundedReceiver<()>) {
+        let (started_tx, started_rx) = mpsc::unbounded();
+        (Self { started_tx, gates: RefCell::new(gates.into_iter().collect()) }, started_rx)
+    }
+}
+impl Worker for WorkerServiceImpl {
+    async fn work(&self) -> u32 {
+        self.started_tx.unbounded_send(()).unwrap();
+        let gate = self.gates.borrow_mut().pop_front();
+        if let Some(gate) = gate {
+            gate.await.unwrap();
+        }
+        42
+    }
+}
+
+#[wasm_bindgen_test]
+async fn drain() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server that executes one request at a time */
+    let (gate_tx, gate_rx) = oneshot::channel();
+    let (service_impl, mut started_rx) = WorkerServiceImpl::new([gate_rx]);
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(service_impl)
+        .with_max_in_flight(1)
+        .build();
+    let server_handle = server.handle();
+    let (server, server_done) = server.remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .build();
+    /* run test, the running request completes and the queued request is rejected */
+    let first = client.work().into_result();
+    let second = client.work().into_result();
+    let (first, (second, _)) = futures_util::future::join(first, async {
+        started_rx.next().await.unwrap();
+        futures_util::future::join(
+            async {
+                /* the first request is only completed once the second one has been rejected */
+                let second = second.await;
+                gate_tx.send(()).unwrap();
+                second
+            },
+            server_handle.shutdown(Duration::from_secs(10)),
+        ).await
+    }).await;
+    assert_eq!(first, Ok(42));
+    assert_eq!(second, Err(web_rpc::Error::Shutdown));
+    server_done.await;
+    /* calls after the shutdown fail immediately */
+    assert_eq!(client.work().into_result().await, Err(web_rpc::Error::Shutdown));
+}
+
+#[wasm_bindgen_test]
+async fn timeout() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server whose request is never released */
+    let (_gate_tx, gate_rx) = oneshot::channel();
+    let (service_impl, mut started_rx) = WorkerServiceImpl::new([gate_rx]);
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(service_impl)
+        .build();
+    let server_handle = server.handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .build();
+    /* run test, the request that outlives the timeout is aborted */
+    let (response, _) = futures_util::future::join(
+        client.work().into_result(),
+        async {
+            started_rx.next().await.unwrap();
+            server_handle.shutdown(Duration::from_millis(10)).await;
+        }
+    ).await;
+    assert_eq!(response, Err(web_rpc::Error::Shutdown));
+    /* shutting down again resolves immediately */
+    server_handle.shutdown(Duration::ZERO).await;
+}
+
+#[wasm_bindgen_test]
+async fn bidirectional() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (interface1, interface2) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create server1 and client1 */
+    let (service_impl1, _started_rx1) = WorkerServiceImpl::new([]);
+    let (client1, server1) = web_rpc::Builder::new(interface1)
+        .with_service::<WorkerService<_>>(service_impl1)
+        .with_client::<WorkerClient>()
+        .build();
+    let server_handle1 = server1.handle();
+    wasm_bindgen_futures::spawn_local(server1);
+    /* create server2 and client2 */
+    let (service_impl2, _started_rx2) = WorkerServiceImpl::new([]);
+    let (client2, server2) = web_rpc::Builder::new(interface2)
+        .with_service::<WorkerService<_>>(service_impl2)
+        .with_client::<WorkerClient>()
+        .build();
+    let (server2, _server_handle2) = server2.remote_handle();
+    wasm_bindgen_futures::spawn_local(server2);
+    /* run test, the request that arrives while or after server1 shuts down is rejected */
+    let (response, _) = futures_util::future::join(
+        client2.work().into_result(),
+        server_handle1.shutdown(Duration::ZERO),
+    ).await;
+    assert_eq!(response, Err(web_rpc::Error::Shutdown));
+    /* client1 keeps working after server1 has shut down */
+    assert_eq!(client1.work().await, 42);
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap, time::Duration};
+
+use wasm_bindgen_test::*;
+
+#[web_rpc::service]
+pub trait Worker {
+    async fn work(interval: Duration) -> u32;
+    fn ping();
+}
+
+struct WorkerServiceImpl;
+impl Worker for WorkerServiceImpl {
+    async fn work(&self, interval: Duration) -> u32 {
+        gloo_timers::future::sleep(interval).await;
+        42
+    }
+    fn ping(&self) {}
+}
+
+#[wasm_bindgen_test]
+async fn stats() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server */
+    let server = web_rpc::Builder::new(server_interface)
+        .with_service::<WorkerService<_>>(WorkerServiceImpl)
+        .build();
+    let server_handle = server.handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<WorkerClient>()
+        .build();
+    /* the request is in flight on both sides while it is being executed */
+    let (response, _) = futures_util::future::join(
+        client.work(Duration::from_millis(20)),
+        async {
+            gloo_timers::future::sleep(Duration::from_millis(5)).await;
+            assert_eq!(client.stats().in_flight, 1);
+            assert_eq!(server_handle.stats().in_flight, 1);
+        }
+    ).await;
+    assert_eq!(response, 42);
+    /* send a notification and abort a request once it has started */
+    client.ping();
+    futures_util::future::select(
+        client.work(Duration::from_secs(10)),
+        gloo_timers::future::sleep(Duration::from_millis(5)),
+    ).await;
+    gloo_timers::future::sleep(Duration::from_millis(5)).await;
+    /* check the statistics */
+    let client_stats = client.stats();
+    let server_stats = server_handle.stats();
+    let requests = HashMap::from([("work", 2), ("ping", 1)]);
+    assert_eq!(client_stats.requests, requests);
+    assert_eq!(server_stats.requests, requests);
+    assert_eq!((client_stats.in_flight, server_stats.in_flight, server_stats.queued), (0, 0, 0));
+    assert_eq!((client_stats.aborts, server_stats.aborts), (1, 1));
+    assert_eq!((client_stats.errors, server_stats.errors), (0, 0));
+    assert_eq!(client_stats.bytes_sent, server_stats.bytes_received);
+    assert_eq!(client_stats.bytes_received, server_stats.bytes_sent);
+}
@@ -0,0 +1,80 @@
+use std::cell::Cell;
+
+use futures_util::FutureExt;
+use wasm_bindgen_test::*;
+
+use health::HealthClientMethods;
+
+mod health {
+    #[web_rpc::service]
+    pub trait Health {
+        fn is_healthy() -> bool;
+    }
+}
+
+#[web_rpc::service]
+pub trait Metrics {
+    fn requests() -> u32;
+}
+
+#[web_rpc::service]
+pub trait Admin: health::Health + Metrics {
+    async fn restart(delay: u32) -> u32;
+}
+
+#[derive(Default)]
+struct AdminServiceImpl {
+    requests: Cell<u32>,
+}
+impl health::Health for AdminServiceImpl {
+    fn is_healthy(&self) -> bool {
+        self.requests.replace(self.requests.get() + 1);
+        true
+    }
+}
+impl Metrics for AdminServiceImpl {
+    fn requests(&self) -> u32 {
+        self.requests.replace(self.requests.get() + 1) + 1
+    }
+}
+impl Admin for AdminServiceImpl {
+    async fn restart(&self, delay: u32) -> u32 {
+        self.requests.replace(self.requests.get() + 1);
+        delay
+    }
+}
+
+#[wasm_bindgen_test]
+async fn supertraits() {
+    console_error_panic_hook::set_once();
+    /* create channel */
+    let channel = web_sys::MessageChannel::new().unwrap();
+    let (server_interface, client_interface) = futures_util::future::join(
+        web_rpc::Interface::new(channel.port1()),
+        web_rpc::Interface::new(channel.port2()),
+    ).await;
+    /* create and spawn server (shuts down when _server_handle is dropped) */
+    let (server, _server_handle) = web_rpc::Builder::new(server_interface)
+        .with_service::<AdminService<_>>(AdminServiceImpl::default())
+        .with_method_limit("is_healthy", 1)
+        .build()
+        .remote_handle();
+    wasm_bindgen_futures::spawn_local(server);
+    /* create client */
+    let client = web_rpc::Builder::new(client_interface)
+        .with_client::<AdminClient>()
+        .build();
+    /* run test, the methods of the supertraits are available on the client */
+    assert!(client.is_healthy().await);
+    assert_eq!(client.restart(10).await, 10);
+    assert_eq!(client.requests().await, 3);
+    /* the clients of the supertraits can also be accessed directly */
+    assert!(client.health().is_healthy().await);
+}
+
+#[wasm_bindgen_test]
+fn methods() {
+    use web_rpc::service::Service;
+    /* the methods of the supertraits come first */
+    assert_eq!(AdminService::<AdminServiceImpl>::methods(), ["is_healthy", "requests", "restart"]);
+}